@@ -3,6 +3,7 @@ import { writable } from 'svelte/store';
 import { confirm } from '@tauri-apps/plugin-dialog';
 import { updateProgressVisible, checkForUpdates } from './updater';
 import { shinyStatus, shinyUrl, shinyError } from './shinyListener';
+import { shinyErrorMessage } from './shiny';
 import { openBrowserWindow } from './windowManager'; // Update import
 
 // App initialization stages
@@ -78,11 +79,12 @@ export async function initializeApp(): Promise<void> {
       
     } catch (error) {
       console.error('Failed to start Shiny:', error);
+      const message = shinyErrorMessage(error);
       initStatus.set('error');
-      initMessage.set(`Failed to start Shiny: ${error}`);
-      initError.set(String(error));
+      initMessage.set(`Failed to start Shiny: ${message}`);
+      initError.set(message);
       shinyStatus.set('error');
-      shinyError.set(String(error));
+      shinyError.set(message);
       shinyLaunchStatus.set('error');
     }
   } catch (error) {
@@ -1,12 +1,36 @@
 import { invoke } from '@tauri-apps/api/core';
 import { writable } from 'svelte/store';
 import { stopBrowserMonitoring } from './windowManager';
+import type { LogEntry } from './shinyListener';
 
 // Status tracking for the Shiny process
 export const shinyStatus = writable<'idle' | 'starting' | 'running' | 'stopping' | 'stopped' | 'error'>('idle');
 export const shinyUrl = writable<string | null>(null);
 export const shinyError = writable<string | null>(null);
 
+/**
+ * Error returned by start_r_shiny: `kind` identifies the failure, `message`
+ * is ready to show to the user and `hint`, when set, says what to do about it
+ */
+export interface ShinyStartError {
+  kind: string;
+  message: string;
+  hint: string | null;
+  [detail: string]: unknown;
+}
+
+/**
+ * Get a readable message from a command error, typed or plain string,
+ * followed by the remediation hint if there is one
+ */
+export function shinyErrorMessage(error: unknown): string {
+  if (error && typeof error === 'object' && 'message' in error) {
+    const { message, hint } = error as ShinyStartError;
+    return hint ? `${message}\n${hint}` : String(message);
+  }
+  return String(error);
+}
+
 /**
  * Launch the Shiny application
  */
@@ -25,7 +49,7 @@ export async function launchShinyApp(): Promise<string> {
     return url;
   } catch (error) {
     console.error('Failed to start Shiny app:', error);
-    shinyError.set(String(error));
+    shinyError.set(shinyErrorMessage(error));
     shinyStatus.set('error');
     throw error;
   }
@@ -49,4 +73,170 @@ export async function stopShinyApp(): Promise<void> {
     shinyStatus.set('error');
     throw error;
   }
-}
\ No newline at end of file
+}
+
+/**
+ * Restart the Shiny application, keeping its URL when the port is still free
+ */
+export async function restartShinyApp(): Promise<string> {
+  try {
+    shinyStatus.set('starting');
+    shinyError.set(null);
+
+    const url = await invoke<string>('restart_r_shiny');
+
+    shinyUrl.set(url);
+    shinyStatus.set('running');
+
+    return url;
+  } catch (error) {
+    console.error('Failed to restart Shiny app:', error);
+    shinyError.set(shinyErrorMessage(error));
+    shinyStatus.set('error');
+    throw error;
+  }
+}
+
+/**
+ * Quit the whole application; the backend stops Shiny on the way out
+ */
+export async function quitApp(): Promise<void> {
+  stopBrowserMonitoring();
+  await invoke<void>('quit_app');
+}
+
+/**
+ * Abort a Shiny start that is still in progress
+ */
+export async function cancelShinyStart(): Promise<void> {
+  try {
+    await invoke<void>('cancel_shiny_start');
+  } catch (error) {
+    console.error('Failed to cancel Shiny start:', error);
+  }
+}
+
+/**
+ * A network interface the app can be shared on
+ */
+export interface ShareInterface {
+  name: string;
+  address: string;
+}
+
+/**
+ * An active LAN share; `url` carries the access token
+ */
+export interface LanShare {
+  interface: string;
+  address: string;
+  port: number;
+  url: string;
+}
+
+export const lanShare = writable<LanShare | null>(null);
+
+/**
+ * List the interfaces the app can be shared on
+ */
+export async function listShareInterfaces(): Promise<ShareInterface[]> {
+  return invoke<ShareInterface[]>('list_share_interfaces');
+}
+
+/**
+ * Make the running app reachable from other machines, e.g. for teaching
+ * assistants. Sharing turns itself off when the Shiny session ends
+ */
+export async function startLanShare(address?: string): Promise<LanShare> {
+  const share = await invoke<LanShare>('start_lan_share', { address: address ?? null });
+  lanShare.set(share);
+  return share;
+}
+
+/**
+ * Turn LAN sharing off
+ */
+export async function stopLanShare(): Promise<void> {
+  try {
+    await invoke<void>('stop_lan_share');
+  } catch (error) {
+    console.error('Failed to stop LAN sharing:', error);
+  }
+  lanShare.set(null);
+}
+
+/**
+ * Fetch buffered R output, oldest first. `since` is the last sequence
+ * number already seen; `level` is the lowest severity to include; `appId`
+ * reads an app from the registry instead of the main app
+ */
+export async function getShinyLogs(
+  since?: number,
+  level?: 'info' | 'warning' | 'error',
+  limit?: number,
+  appId?: string
+): Promise<LogEntry[]> {
+  return invoke<LogEntry[]>('get_shiny_logs', {
+    appId: appId ?? null,
+    since: since ?? null,
+    level: level ?? null,
+    limit: limit ?? null
+  });
+}
+
+/**
+ * Open the folder with the launcher and R log files, e.g. to attach them
+ * to a bug report
+ */
+export async function openLogFolder(): Promise<void> {
+  try {
+    await invoke<void>('open_log_folder');
+  } catch (error) {
+    console.error('Failed to open log folder:', error);
+  }
+}
+
+/**
+ * An app from `assets/apps/<id>`, with the state of its own R process
+ */
+export interface ShinyAppInfo {
+  id: string;
+  name: string;
+  version: string | null;
+  dir: string;
+  entry: string | null;
+  status: {
+    state: string;
+    url: string | null;
+    port: number | null;
+    message: string | null;
+  };
+}
+
+/**
+ * List the apps in the registry. Each runs on its own port, so several can
+ * be open at once
+ */
+export async function listShinyApps(): Promise<ShinyAppInfo[]> {
+  return invoke<ShinyAppInfo[]>('list_shiny_apps');
+}
+
+/**
+ * Start an app from the registry; resolves to its URL once it is serving.
+ * Its events arrive as `shiny-app-event` tagged with `app_id`, which
+ * `registryApps` follows
+ */
+export async function startShinyApp(id: string): Promise<string> {
+  return invoke<string>('start_shiny_app', { id });
+}
+
+/**
+ * Stop an app from the registry, or abort its start
+ */
+export async function stopRegistryApp(id: string): Promise<void> {
+  try {
+    await invoke<void>('stop_shiny_app', { id });
+  } catch (error) {
+    console.error(`Failed to stop app ${id}:`, error);
+  }
+}
@@ -1,5 +1,6 @@
-import { listen } from '@tauri-apps/api/event'
-import { writable, type Writable } from 'svelte/store'
+import { listen, type UnlistenFn } from '@tauri-apps/api/event'
+import { get, writable, type Writable } from 'svelte/store'
+import { getShinyLogs, lanShare, type ShinyAppInfo } from './shiny'
 
 // Define types for better type safety
 type ShinyStatus = 'idle' | 'running' | 'stopped' | 'error' | string;
@@ -9,8 +10,172 @@ export const shinyStatus: Writable<ShinyStatus> = writable('idle')
 export const shinyUrl = writable('')
 export const shinyError = writable<string | null>(null)
 
+// Lifecycle state reported by the backend supervisor
+export type ShinyState =
+  | 'idle'
+  | 'spawning'
+  | 'loading_packages'
+  | 'ready'
+  | 'crashed'
+  | 'stopping'
+  | 'stopped'
+
+export interface ShinyStateChange {
+  from: ShinyState
+  to: ShinyState
+  url: string | null
+  pid: number | null
+  message: string | null
+}
+
+export const shinyState = writable<ShinyState>('idle')
+
+// Details of the last crash after automatic restarts gave up
+export interface CrashReport {
+  exit_status: string
+  exit_code: number | null
+  stderr_tail: string[]
+  recent_crashes: number
+  restarting: boolean
+  timestamp: number
+}
+
+export const shinyCrash = writable<CrashReport | null>(null)
+
+// Progress while R loads the app's declared packages
+export interface PackageProgress {
+  loaded: number
+  total: number
+  current_package: string | null
+  eta_secs: number | null
+}
+
+export const packageProgress = writable<PackageProgress | null>(null)
+
+// A line of R output, as buffered by the backend
+export interface LogEntry {
+  seq: number
+  timestamp: number
+  stream: 'stdout' | 'stderr'
+  level: 'info' | 'warning' | 'error'
+  line: string
+}
+
+// Lines kept for the R console panel
+const MAX_LOG_LINES = 1000
+
+export const shinyLogs = writable<LogEntry[]>([])
+
+// Adds lines in sequence order, keeping each seq once, since a line can
+// arrive both live and in a catch-up fetch
+export function appendShinyLogs(entries: LogEntry[]) {
+  shinyLogs.update((logs) => {
+    const last = logs.length ? logs[logs.length - 1].seq : -1
+    if (entries.every((e) => e.seq > last)) {
+      return logs.concat(entries).slice(-MAX_LOG_LINES)
+    }
+    const bySeq = new Map(logs.map((e) => [e.seq, e]))
+    for (const e of entries) bySeq.set(e.seq, e)
+    return [...bySeq.values()].sort((a, b) => a.seq - b.seq).slice(-MAX_LOG_LINES)
+  })
+}
+
+// An event from an app in the registry, tagged with the app's id
+export interface ShinyAppEvent {
+  app_id: string
+  event: string
+  payload: unknown
+}
+
+// Apps in the registry by id, kept up to date from their events
+export const registryApps = writable<Record<string, ShinyAppInfo>>({})
+
+function applyAppEvent({ app_id, event, payload }: ShinyAppEvent) {
+  registryApps.update((apps) => {
+    const app = apps[app_id]
+    if (!app) return apps
+    const status = { ...app.status }
+    switch (event) {
+      case 'shiny-state': {
+        const change = payload as ShinyStateChange
+        status.state = change.to
+        status.url = change.url
+        status.message = change.message
+        break
+      }
+      case 'shiny-started':
+        status.url = payload as string
+        break
+      case 'shiny-stopped':
+        status.state = 'stopped'
+        status.url = null
+        break
+      case 'shiny-status':
+      case 'shiny-error':
+        status.message = payload as string
+        break
+      default:
+        return apps
+    }
+    return { ...apps, [app_id]: { ...app, status } }
+  })
+}
+
+let logListener: Promise<UnlistenFn> | null = null
+// Highest seq known to be held along with everything before it
+let syncedSeq: number | undefined
+
+function listenForLogs(): Promise<UnlistenFn> {
+  logListener ??= listen<LogEntry>('shiny-log', (event) => {
+    appendShinyLogs([event.payload])
+  })
+  return logListener
+}
+
+// Catches up on lines printed before anything was listening. Subscribes
+// before fetching, so a line printed in between arrives live if not in the
+// fetch
+export async function syncShinyLogs(limit = 500) {
+  await listenForLogs()
+  appendShinyLogs(await getShinyLogs(syncedSeq, undefined, limit))
+  const logs = get(shinyLogs)
+  if (logs.length) syncedSeq = logs[logs.length - 1].seq
+}
+
 // Function to initialize listeners
 export function initShinyListeners() {
+  listen<ShinyStateChange>('shiny-state', (event) => {
+    console.log(`Shiny state: ${event.payload.from} -> ${event.payload.to}`)
+    shinyState.set(event.payload.to)
+  })
+
+  listen<CrashReport>('shiny-crashed', (event) => {
+    console.error('Shiny crashed:', event.payload)
+    shinyCrash.set(event.payload)
+    shinyError.set(`R stopped unexpectedly (${event.payload.exit_status})`)
+    shinyStatus.set('error')
+  })
+
+  listen<PackageProgress>('shiny-package-progress', (event) => {
+    packageProgress.set(event.payload)
+  })
+
+  listen<ShinyStateChange>('shiny-state', (event) => {
+    // Progress only means something while packages load
+    if (event.payload.to !== 'loading_packages') packageProgress.set(null)
+  })
+
+  listenForLogs()
+
+  listen<ShinyAppEvent>('shiny-app-event', (event) => {
+    applyAppEvent(event.payload)
+  })
+
+  listen('lan-share-stopped', () => {
+    console.log('LAN sharing stopped with the Shiny session')
+    lanShare.set(null)
+  })
+
   listen('shiny-status', (event) => {
     console.log('Shiny status:', event.payload)
     shinyStatus.set(event.payload as string)
@@ -20,6 +20,8 @@ interface UpdateProgress {
   total?: number;
   complete: boolean;
   error?: string;
+  // Set in portable mode, where the package is staged instead of installed
+  stagedPath?: string | null;
 }
 
 // Create a store for managing the progress dialog visibility
@@ -91,6 +93,19 @@ export async function checkForUpdates() {
         // Hide progress dialog
         updateProgressVisible.set(false);
         
+        // A portable copy only stages the package; relaunching would just
+        // start the old version and offer the same update again
+        const progress: UpdateProgress = JSON.parse(await invoke<string>('get_update_progress'));
+        if (progress.stagedPath) {
+          await message(
+            'Update has been downloaded but not installed, since this is a portable copy.\n\n' +
+            `The package is at:\n${progress.stagedPath}\n\n` +
+            'Close the application and replace this copy with it to finish updating.',
+            { title: 'Update Downloaded' }
+          );
+          return;
+        }
+
         // When complete, show success message and relaunch
         await message('Update has been downloaded and will be installed now. The application will restart.', {
           title: 'Update Ready'
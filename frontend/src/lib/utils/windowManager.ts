@@ -60,8 +60,9 @@ function setupBrowserMonitoring(shinyUrl: string): void {
       const timeoutId = setTimeout(() => controller.abort(), 2000); // 2 second timeout
       
       try {
-        // Use GET request with cache busting to avoid cached responses
-        const response = await fetch(`${shinyUrl}?_=${Date.now()}`, { 
+        // Ping the origin; the URL's secret is only needed once, to log in
+        const origin = new URL(shinyUrl).origin;
+        const response = await fetch(`${origin}/?_=${Date.now()}`, { 
           method: 'HEAD',
           mode: 'no-cors',
           signal: controller.signal
@@ -0,0 +1,215 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter};
+
+use crate::manifest::{AppManifest, RRequirement, MANIFEST_FILE};
+use crate::process;
+use crate::r_packages::PackageRequirement;
+use crate::r_shiny::{self, emit, StatusSink};
+use crate::runtime::{DataDirs, RuntimeConfig, RuntimeState};
+use crate::settings::SettingsState;
+use crate::shiny_error::ShinyStartError;
+use crate::supervisor::{ShinyState, ShinyStatus, ShinySupervisor};
+
+/// Directory under the assets holding one subdirectory per app.
+pub const APPS_DIR: &str = "apps";
+
+/// An app found under `assets/apps/<id>`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ShinyApp {
+    pub id: String,
+    pub name: String,
+    pub version: Option<String>,
+    pub dir: PathBuf,
+    /// Script serving the app; `None` uses the shared `start-shiny.R`.
+    pub entry: Option<PathBuf>,
+    pub r: RRequirement,
+    pub packages: Vec<PackageRequirement>,
+}
+
+/// An app with its current state, as returned by `list_shiny_apps`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ShinyAppInfo {
+    #[serde(flatten)]
+    pub app: ShinyApp,
+    pub status: ShinyStatus,
+}
+
+/// Payload of the `shiny-app-event` event. Apps in the registry report
+/// through it, so their events can't be confused with each other's.
+#[derive(Debug, Clone, Serialize)]
+struct AppEvent<'a> {
+    app_id: &'a str,
+    event: &'a str,
+    payload: serde_json::Value,
+}
+
+fn valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Finds every `assets/apps/<id>/app.toml`. Apps with an invalid id or
+/// manifest are skipped with a warning.
+pub fn discover(assets_dir: &Path) -> Vec<ShinyApp> {
+    let Ok(entries) = std::fs::read_dir(assets_dir.join(APPS_DIR)) else {
+        return Vec::new();
+    };
+    let mut apps: Vec<ShinyApp> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|dir| dir.join(MANIFEST_FILE).is_file())
+        .filter_map(|dir| {
+            let id = dir.file_name()?.to_str()?.to_string();
+            if !valid_id(&id) {
+                log::warn!("Skipping app with invalid id '{}'", id);
+                return None;
+            }
+            let manifest = AppManifest::load(&dir)
+                .map_err(|e| log::warn!("Skipping app '{}': {}", id, e))
+                .ok()?;
+            Some(ShinyApp {
+                name: manifest.name.unwrap_or_else(|| id.clone()),
+                version: manifest.version,
+                entry: manifest.entry.map(|entry| dir.join(entry)),
+                r: manifest.r,
+                packages: manifest.packages,
+                dir,
+                id,
+            })
+        })
+        .collect();
+    apps.sort_by(|a, b| a.id.cmp(&b.id));
+    log::info!("Found {} app(s) in the registry", apps.len());
+    apps
+}
+
+fn app_sink(app_handle: &AppHandle, app_id: &str) -> StatusSink {
+    let app_handle = app_handle.clone();
+    let app_id = app_id.to_string();
+    Arc::new(move |event, payload| {
+        let event = AppEvent {
+            app_id: &app_id,
+            event,
+            payload,
+        };
+        app_handle
+            .emit("shiny-app-event", event)
+            .unwrap_or_else(|e| log::warn!("Failed to emit shiny-app-event: {}", e));
+    })
+}
+
+/// Managed state holding the registry's apps, each with a supervisor of its
+/// own so several can run side by side.
+pub struct AppRegistry {
+    apps: BTreeMap<String, (ShinyApp, ShinySupervisor)>,
+}
+
+impl AppRegistry {
+    /// Discovers the apps and sets up their supervisors, cleaning up any R
+    /// a previous session left running for them.
+    pub fn new(app_handle: &AppHandle, assets_dir: &Path, data_dirs: &DataDirs) -> Self {
+        let apps = discover(assets_dir)
+            .into_iter()
+            .map(|app| {
+                let pid_file = data_dirs.data.join(format!("shiny-{}.pid", app.id));
+                process::clean_up_orphan(&pid_file);
+                let supervisor =
+                    ShinySupervisor::new(app_sink(app_handle, &app.id)).with_pid_file(pid_file);
+                (app.id.clone(), (app, supervisor))
+            })
+            .collect();
+        Self { apps }
+    }
+
+    fn get(&self, id: &str) -> Result<&(ShinyApp, ShinySupervisor), String> {
+        self.apps
+            .get(id)
+            .ok_or_else(|| format!("No app with id '{}'", id))
+    }
+
+    pub fn supervisor(&self, id: &str) -> Result<&ShinySupervisor, String> {
+        self.get(id).map(|(_, supervisor)| supervisor)
+    }
+
+    pub fn supervisors(&self) -> impl Iterator<Item = &ShinySupervisor> {
+        self.apps.values().map(|(_, supervisor)| supervisor)
+    }
+}
+
+/// The runtime config for `app`: its directory and entry script, and cache
+/// and working directories of its own so apps running side by side don't
+/// share handshake files.
+fn app_config(base: &RuntimeConfig, app: &ShinyApp) -> Result<RuntimeConfig, String> {
+    let mut config = base.clone();
+    config.shiny_app = app.dir.clone();
+    if let Some(entry) = &app.entry {
+        if !entry.is_file() {
+            return Err(format!(
+                "Entry script for '{}' not found at {}",
+                app.id,
+                entry.display()
+            ));
+        }
+        config.start_shiny = entry.clone();
+    }
+    config.data.cache = base.data.cache.join(APPS_DIR).join(&app.id);
+    config.data.shiny_data = base.data.shiny_data.join(&app.id);
+    for dir in [&config.data.cache, &config.data.shiny_data] {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    Ok(config)
+}
+
+/// Lists the apps in the registry and their state.
+#[tauri::command]
+pub fn list_shiny_apps(registry: tauri::State<'_, AppRegistry>) -> Vec<ShinyAppInfo> {
+    registry
+        .apps
+        .values()
+        .map(|(app, supervisor)| ShinyAppInfo {
+            app: app.clone(),
+            status: supervisor.status(),
+        })
+        .collect()
+}
+
+/// Starts app `id` from the registry, on a port of its own. Returns its URL
+/// at once if it is already running.
+#[tauri::command]
+pub async fn start_shiny_app(
+    id: String,
+    registry: tauri::State<'_, AppRegistry>,
+    runtime: tauri::State<'_, RuntimeState>,
+    settings: tauri::State<'_, SettingsState>,
+) -> Result<String, ShinyStartError> {
+    let (app, supervisor) = registry.get(&id)?;
+    let status = supervisor.status();
+    if let (ShinyState::Ready, Some(url)) = (status.state, status.url) {
+        return Ok(url);
+    }
+    let config = runtime.config().and_then(|base| app_config(base, app));
+    log::info!("Starting app '{}'", id);
+    r_shiny::start(config, settings.current(), supervisor, None).await
+}
+
+/// Stops app `id`, or aborts its start.
+#[tauri::command]
+pub async fn stop_shiny_app(
+    id: String,
+    registry: tauri::State<'_, AppRegistry>,
+    settings: tauri::State<'_, SettingsState>,
+) -> Result<(), String> {
+    let (_, supervisor) = registry.get(&id)?;
+    supervisor.cancel_start();
+    supervisor
+        .stop(settings.current().launcher.shutdown_grace())
+        .await?;
+    emit(supervisor.sink(), "shiny-stopped", ());
+    Ok(())
+}
@@ -0,0 +1,201 @@
+use regex::Regex;
+use std::sync::OnceLock;
+
+use crate::shiny_error::ShinyStartError;
+
+/// A known failure: how to spot it in a stderr line and which error it is.
+/// `build` gets the match and the line after it, since R often puts the
+/// detail on a line of its own.
+struct Pattern {
+    regex: Regex,
+    build: fn(&regex::Captures, Option<&str>) -> ShinyStartError,
+}
+
+fn patterns() -> &'static [Pattern] {
+    static PATTERNS: OnceLock<Vec<Pattern>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        let pattern = |regex: &str, build| Pattern {
+            regex: Regex::new(regex).unwrap(),
+            build,
+        };
+        vec![
+            pattern(
+                r"there is no package called [‘'`]([^’'`]+)[’'`]",
+                |captures, _| ShinyStartError::PackageNotFound {
+                    package: captures[1].to_string(),
+                },
+            ),
+            pattern(
+                r"cannot open file [‘'`]([^’'`]+)[’'`]",
+                |captures, _| ShinyStartError::FileNotFound {
+                    path: captures[1].to_string(),
+                },
+            ),
+            pattern(
+                r"(?i)address already in use|Failed to create server",
+                |_, _| ShinyStartError::PortInUse,
+            ),
+            pattern(r"Error in parse\(.*?\)\s*:\s*(.*)", |captures, next| {
+                let detail = captures[1].trim();
+                let detail = if detail.is_empty() {
+                    next.unwrap_or_default().trim()
+                } else {
+                    detail
+                };
+                ShinyStartError::ParseError {
+                    detail: detail.to_string(),
+                }
+            }),
+            // Only errors: R survives `Setting LC_CTYPE failed` and the like,
+            // which show up wherever the configured locale isn't installed
+            pattern(
+                r"(?i)invalid multibyte (?:string|character)|invalid in this locale",
+                |captures, _| ShinyStartError::LocaleError {
+                    detail: captures[0].to_string(),
+                },
+            ),
+            pattern(
+                r"cannot allocate vector of size ([\d.]+ \w+)",
+                |captures, _| ShinyStartError::OutOfMemory {
+                    size: captures[1].to_string(),
+                },
+            ),
+        ]
+    })
+}
+
+/// Looks through what R wrote to stderr for a known cause of failure. The
+/// last line that matches wins, since an earlier problem may have been
+/// recovered from.
+pub fn classify(stderr: &[String]) -> Option<ShinyStartError> {
+    stderr.iter().enumerate().rev().find_map(|(index, line)| {
+        let next = stderr.get(index + 1).map(String::as_str);
+        patterns().iter().find_map(|pattern| {
+            let captures = pattern.regex.captures(line)?;
+            Some((pattern.build)(&captures, next))
+        })
+    })
+}
+
+/// The classified cause of a failure if R said what it was, otherwise
+/// `fallback`.
+pub fn diagnose(stderr: &[String], fallback: ShinyStartError) -> ShinyStartError {
+    match classify(stderr) {
+        Some(error) => {
+            log::warn!("Diagnosed R failure: {}", error);
+            error
+        }
+        None => fallback,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|line| line.to_string()).collect()
+    }
+
+    fn kind(stderr: &[&str]) -> Option<&'static str> {
+        classify(&lines(stderr)).map(|error| error.kind())
+    }
+
+    #[test]
+    fn missing_package() {
+        let error = classify(&lines(&[
+            "Error in library(DT) : there is no package called ‘DT’",
+        ]));
+        assert!(
+            matches!(error, Some(ShinyStartError::PackageNotFound { package }) if package == "DT")
+        );
+    }
+
+    #[test]
+    fn missing_file() {
+        let error = classify(&lines(&[
+            "Warning in file(filename, \"r\", encoding = encoding) :",
+            "  cannot open file 'app/global.R': No such file or directory",
+        ]));
+        assert!(
+            matches!(error, Some(ShinyStartError::FileNotFound { path }) if path == "app/global.R")
+        );
+    }
+
+    #[test]
+    fn port_in_use() {
+        assert_eq!(
+            kind(&["ERROR: [on_request_read] address already in use"]),
+            Some("port_in_use")
+        );
+        assert_eq!(
+            kind(&["Error in initialize(...) : Failed to create server"]),
+            Some("port_in_use")
+        );
+    }
+
+    #[test]
+    fn parse_error_with_detail_on_next_line() {
+        let error = classify(&lines(&[
+            "Error in parse(file, keep.source = FALSE, srcfile = NULL) : ",
+            "  app.R:12:3: unexpected '}'",
+        ]));
+        assert!(
+            matches!(error, Some(ShinyStartError::ParseError { detail }) if detail == "app.R:12:3: unexpected '}'")
+        );
+    }
+
+    #[test]
+    fn locale_error() {
+        assert_eq!(
+            kind(&["Error in gsub(\"x\", \"\", s) : input string 1 is invalid in this locale"]),
+            Some("locale_error")
+        );
+        assert_eq!(
+            kind(&["Error in nchar(x) : invalid multibyte string, element 1"]),
+            Some("locale_error")
+        );
+    }
+
+    #[test]
+    fn locale_warnings_are_not_failures() {
+        let stderr = [
+            "During startup - Warning messages:",
+            "1: Setting LC_CTYPE failed, using \"C\" ",
+            "2: OS reports request to set locale to \"en_US.UTF-8\" cannot be honored ",
+        ];
+        assert_eq!(kind(&stderr), None);
+        let fallback = ShinyStartError::Launch("timed out".to_string());
+        assert_eq!(diagnose(&lines(&stderr), fallback).kind(), "launch");
+    }
+
+    #[test]
+    fn out_of_memory() {
+        let error = classify(&lines(&["Error: cannot allocate vector of size 1.5 Gb"]));
+        assert!(matches!(error, Some(ShinyStartError::OutOfMemory { size }) if size == "1.5 Gb"));
+    }
+
+    #[test]
+    fn last_match_wins() {
+        assert_eq!(
+            kind(&[
+                "Error in library(DT) : there is no package called ‘DT’",
+                "Error: cannot allocate vector of size 2 Gb",
+            ]),
+            Some("out_of_memory")
+        );
+        assert_eq!(
+            kind(&[
+                "Error: cannot allocate vector of size 2 Gb",
+                "Error in library(DT) : there is no package called ‘DT’",
+            ]),
+            Some("package_not_found")
+        );
+    }
+
+    #[test]
+    fn unknown_output() {
+        assert_eq!(kind(&["Listening on http://127.0.0.1:1234"]), None);
+        assert_eq!(kind(&[]), None);
+    }
+}
@@ -0,0 +1,146 @@
+use regex::Regex;
+use serde::Deserialize;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Environment variable telling R where to report the address it bound.
+pub const ADDRESS_FILE_VAR: &str = "RE_SHINY_ADDRESS_FILE";
+
+/// Name of the address file, in the cache directory.
+pub const ADDRESS_FILE: &str = "shiny-address.json";
+
+/// Address R reports once its server is listening, written as
+/// `{"host": "127.0.0.1", "port": 3842}` to the file named by
+/// `RE_SHINY_ADDRESS_FILE`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct BoundAddress {
+    pub host: String,
+    pub port: u16,
+}
+
+impl BoundAddress {
+    /// URL to reach the server. A wildcard bind is reached through
+    /// `fallback_host`.
+    pub fn url(&self, fallback_host: &str) -> String {
+        let host = match self.host.parse::<IpAddr>() {
+            Ok(ip) if ip.is_unspecified() => fallback_host.to_string(),
+            _ => self.host.clone(),
+        };
+        if host.contains(':') && !host.starts_with('[') {
+            format!("http://[{}]:{}", host, self.port)
+        } else {
+            format!("http://{}:{}", host, self.port)
+        }
+    }
+}
+
+/// Reads the address R reported, if it has written the file yet.
+pub fn read_address_file(path: &Path) -> Option<BoundAddress> {
+    let text = std::fs::read_to_string(path).ok()?;
+    // A parse failure may just mean R is halfway through writing it
+    serde_json::from_str::<BoundAddress>(&text)
+        .ok()
+        .filter(|address| address.port != 0)
+}
+
+/// Picks the address out of Shiny's `Listening on http://host:port` line,
+/// for apps that do not write the address file.
+pub fn parse_listening_line(line: &str) -> Option<BoundAddress> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = PATTERN
+        .get_or_init(|| Regex::new(r"Listening on https?://(\[[^\]]+\]|[^\s:/]+):(\d+)").unwrap());
+    let captures = pattern.captures(line)?;
+    let host = captures[1]
+        .trim_matches(|c| c == '[' || c == ']')
+        .to_string();
+    let port = captures[2].parse().ok().filter(|port| *port != 0)?;
+    Some(BoundAddress { host, port })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn address(host: &str, port: u16) -> BoundAddress {
+        BoundAddress {
+            host: host.to_string(),
+            port,
+        }
+    }
+
+    fn temp_file(name: &str, contents: Option<&str>) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{}", std::process::id(), name));
+        match contents {
+            Some(text) => std::fs::write(&path, text).unwrap(),
+            None => {
+                let _ = std::fs::remove_file(&path);
+            }
+        }
+        path
+    }
+
+    #[test]
+    fn parses_shiny_listening_lines() {
+        assert_eq!(
+            parse_listening_line("Listening on http://127.0.0.1:3842"),
+            Some(address("127.0.0.1", 3842))
+        );
+        assert_eq!(
+            parse_listening_line("Listening on http://[::1]:4000"),
+            Some(address("::1", 4000))
+        );
+        assert_eq!(
+            parse_listening_line("Listening on http://localhost:5000/"),
+            Some(address("localhost", 5000))
+        );
+    }
+
+    #[test]
+    fn ignores_other_lines() {
+        assert_eq!(
+            parse_listening_line("Loading required package: shiny"),
+            None
+        );
+        assert_eq!(
+            parse_listening_line("Listening on http://127.0.0.1:0"),
+            None
+        );
+        assert_eq!(parse_listening_line("Listening on a socket"), None);
+    }
+
+    #[test]
+    fn reads_the_address_file() {
+        let path = temp_file(
+            "address-ok.json",
+            Some(r#"{"host": "127.0.0.1", "port": 3842}"#),
+        );
+        assert_eq!(read_address_file(&path), Some(address("127.0.0.1", 3842)));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn address_file_is_absent_until_complete() {
+        let missing = temp_file("address-missing.json", None);
+        assert_eq!(read_address_file(&missing), None);
+
+        let partial = temp_file("address-partial.json", Some(r#"{"host": "127.0."#));
+        assert_eq!(read_address_file(&partial), None);
+        let _ = std::fs::remove_file(partial);
+
+        let zero = temp_file("address-zero.json", Some(r#"{"host": "::1", "port": 0}"#));
+        assert_eq!(read_address_file(&zero), None);
+        let _ = std::fs::remove_file(zero);
+    }
+
+    #[test]
+    fn url_brackets_ipv6_and_replaces_wildcards() {
+        assert_eq!(address("::1", 80).url("127.0.0.1"), "http://[::1]:80");
+        assert_eq!(
+            address("0.0.0.0", 80).url("127.0.0.1"),
+            "http://127.0.0.1:80"
+        );
+        assert_eq!(address("127.0.0.1", 80).url("::1"), "http://127.0.0.1:80");
+    }
+}
@@ -0,0 +1,166 @@
+use serde::Serialize;
+use std::net::{IpAddr, TcpListener};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::proxy::Proxy;
+use crate::settings::SettingsState;
+use crate::supervisor::{ShinyState, ShinySupervisor};
+
+/// How often an active share checks whether its session has ended.
+const SESSION_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A network interface the app can be shared on.
+#[derive(Debug, Clone, Serialize)]
+pub struct ShareInterface {
+    pub name: String,
+    pub address: IpAddr,
+}
+
+/// An active share, returned by `start_lan_share` and `get_lan_share`.
+#[derive(Debug, Clone, Serialize)]
+pub struct LanShare {
+    pub interface: String,
+    pub address: IpAddr,
+    pub port: u16,
+    /// Includes the access token; anyone with it can open the app.
+    pub url: String,
+}
+
+struct ActiveShare {
+    info: LanShare,
+    /// Dropping it closes the listener and every shared connection.
+    _proxy: Proxy,
+}
+
+/// Managed state holding the share, if one is active. Sharing is off until a
+/// user turns it on and ends with the Shiny session it was started in.
+#[derive(Clone, Default)]
+pub struct LanShareState {
+    active: Arc<Mutex<Option<ActiveShare>>>,
+}
+
+impl LanShareState {
+    pub fn current(&self) -> Option<LanShare> {
+        self.active.lock().unwrap().as_ref().map(|s| s.info.clone())
+    }
+
+    /// Turns sharing off. Returns false if it was not on.
+    pub fn stop(&self) -> bool {
+        let stopped = self.active.lock().unwrap().take().is_some();
+        if stopped {
+            log::info!("LAN sharing stopped");
+        }
+        stopped
+    }
+}
+
+/// Non-loopback interfaces other machines may reach the app on.
+fn interfaces() -> Result<Vec<ShareInterface>, String> {
+    let interfaces = if_addrs::get_if_addrs()
+        .map_err(|e| format!("Failed to list network interfaces: {}", e))?;
+    Ok(interfaces
+        .into_iter()
+        .filter(|i| !i.is_loopback())
+        .map(|i| ShareInterface {
+            address: i.ip(),
+            name: i.name,
+        })
+        .collect())
+}
+
+/// Lists the interfaces the app can be shared on.
+#[tauri::command]
+pub fn list_share_interfaces() -> Result<Vec<ShareInterface>, String> {
+    interfaces()
+}
+
+/// Makes the running app reachable from other machines on `address`, or the
+/// first IPv4 interface when none is given, behind a fresh access token.
+/// Replaces a share that is already active. Only the main app can be
+/// shared, not apps from the registry.
+#[tauri::command]
+pub async fn start_lan_share(
+    address: Option<IpAddr>,
+    state: tauri::State<'_, LanShareState>,
+    settings: tauri::State<'_, SettingsState>,
+    supervisor: tauri::State<'_, ShinySupervisor>,
+) -> Result<LanShare, String> {
+    if supervisor.status().state != ShinyState::Ready {
+        return Err("Start the app before sharing it".to_string());
+    }
+    let available = interfaces()?;
+    let interface = match address {
+        Some(address) => available.into_iter().find(|i| i.address == address),
+        None => available.into_iter().find(|i| i.address.is_ipv4()),
+    }
+    .ok_or("No network interface to share on")?;
+
+    let upstream = supervisor.upstream(settings.current().launcher.host_ip());
+    state.stop();
+    let proxy = TcpListener::bind((interface.address, 0))
+        .and_then(|listener| Proxy::serve(listener, upstream))
+        .map_err(|e| format!("Failed to share on {}: {}", interface.address, e))?;
+    let info = LanShare {
+        url: proxy.url(&interface.address.to_string()),
+        port: proxy.local_addr().port(),
+        address: interface.address,
+        interface: interface.name,
+    };
+    log::info!(
+        "LAN sharing on {} ({}:{})",
+        info.interface,
+        info.address,
+        info.port
+    );
+    *state.active.lock().unwrap() = Some(ActiveShare {
+        info: info.clone(),
+        _proxy: proxy,
+    });
+
+    end_with_session(state.inner().clone(), supervisor.inner().clone(), info.port);
+    Ok(info)
+}
+
+/// Turns sharing off when the Shiny session it belongs to ends: the app is
+/// stopped or restarted, or R crashed and is not coming back.
+fn end_with_session(state: LanShareState, supervisor: ShinySupervisor, port: u16) {
+    let generation = supervisor.session();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(SESSION_CHECK_INTERVAL).await;
+            // Stopped or replaced by another share
+            if state.current().map(|share| share.port) != Some(port) {
+                return;
+            }
+            if supervisor.session_ended(generation) {
+                state.stop();
+                (supervisor.sink())("lan-share-stopped", serde_json::Value::Null);
+                return;
+            }
+        }
+    });
+}
+
+/// Turns LAN sharing off.
+#[tauri::command]
+pub fn stop_lan_share(state: tauri::State<'_, LanShareState>) -> Result<(), String> {
+    if !state.stop() {
+        return Err("LAN sharing is not on".to_string());
+    }
+    Ok(())
+}
+
+/// Returns the active share, if any.
+#[tauri::command]
+pub fn get_lan_share(state: tauri::State<'_, LanShareState>) -> Option<LanShare> {
+    state.current()
+}
+
+/// Turns sharing off as the app exits.
+pub fn stop_on_exit(app_handle: &AppHandle) {
+    if let Some(state) = app_handle.try_state::<LanShareState>() {
+        state.stop();
+    }
+}
@@ -1,110 +1,67 @@
+mod app_registry;
+mod diagnosis;
+mod handshake;
+mod lan_share;
+mod load_progress;
+mod logging;
+mod manifest;
+mod migration;
+mod portable;
+mod process;
+mod proxy;
+mod r_discovery;
+mod r_env;
+mod r_packages;
 mod r_shiny;
+mod r_version;
+mod readiness;
+mod runtime;
+mod self_test;
+mod settings;
+mod shiny_error;
+mod shiny_log;
+mod supervisor;
 mod update; // Import the update module // Import the R process module
 
-use std::env;
 use std::sync::{Arc, Mutex};
-use tauri::Manager;
+use tauri::{Manager, RunEvent};
 use update::UpdateProgress;
 
-fn set_global_env_vars() {
-    let mut is_dev: bool = cfg!(debug_assertions);
-    //is_dev = false; // Set to false for production
-    // Get the current executable directory for absolute paths
-    let app_dir = std::env::current_exe()
-        .unwrap_or_else(|_| std::path::PathBuf::new())
-        .parent()
-        .map(|p| p.to_path_buf())
-        .unwrap_or_else(|| std::path::PathBuf::new());
-
-    println!("App directory: {:?}", app_dir);
-
-    //if in dev mode then use local path else use appdata path
-    let base_path = if is_dev {
-        std::env::current_dir()
-            .unwrap_or_else(|_| std::path::PathBuf::new())
-            .join("assets")
-    } else {
-        std::env::current_exe()
-            .ok()
-            .and_then(|exe_path| exe_path.parent().map(|p| p.to_path_buf()))
-            .unwrap_or_else(|| ".".into())
-            .join("assets")
-    };
-    println!("Base path: {:?}", base_path);
-    std::fs::create_dir_all(&base_path).unwrap_or_default();
-
-    // With these lines:
-    let r_home_path = base_path.join("r-win");
-    let r_lib_path = r_home_path.join("library");
-    let rscript_path = r_home_path.join("bin").join("Rscript.exe");
-    let shiny_app_path = base_path.join("shiny");
-    let start_shiny_path = base_path.join("start-shiny.R");
-    let shiny_url = if is_dev {
-        // Use localhost in development mode
-        "http://127.0.0.1".to_string()
-    } else {
-        // In production, could use a different host if needed
-        "http://127.0.0.1".to_string()
-    };
-    // For environment variables, convert paths to strings:
-    let r_home = r_home_path.to_string_lossy().to_string();
-    let r_lib = r_lib_path.to_string_lossy().to_string();
-    let rscript = rscript_path.to_string_lossy().to_string();
-    let shiny_app = shiny_app_path.to_string_lossy().to_string();
-    let start_shiny = start_shiny_path.to_string_lossy().to_string();
-
-    // Set environment variables:
-    env::set_var("RHOME", &r_home);
-    env::set_var("R_HOME_DIR", &r_home);
-    env::set_var("R_LIBS", &r_lib);
-    env::set_var("R_LIB_PATH", &r_lib);
-    env::set_var("RSCRIPT_PATH", &rscript);
-    env::set_var("SHINY_APP_PATH", &shiny_app);
-    env::set_var("START_SHINY_PATH", &start_shiny);
-    env::set_var("SHINY_URL", &shiny_url);
-
-    println!("Environment Variables Set:");
-    println!("  R_HOME_DIR = {}", r_home);
-    println!("  R_LIBS = {}", r_lib);
-    println!("  RSCRIPT_PATH = {}", rscript);
-    println!("  SHINY_APP_PATH = {}", shiny_app);
-    println!("  START_SHINY_PATH = {}", start_shiny);
-    println!("  SHINY_URL = {}", shiny_url);
-    println!("Checking if files exist:");
-    println!(
-        "  Rscript exists: {}",
-        std::path::Path::new(&rscript_path).exists()
-    );
-    println!(
-        "  start_shiny.R exists: {}",
-        std::path::Path::new(&start_shiny_path).exists()
-    );
-    println!(
-        "  shiny_app_path exists: {}",
-        std::path::Path::new(&shiny_app_path).exists()
-    );
-}
-
 #[tauri::command]
 fn greet(name: &str) -> String {
     format!("Hello, {}!", name)
 }
 
 pub fn run() {
+    let context = tauri::generate_context!();
+    let portable_root = portable::detect();
+    if self_test::requested() {
+        std::process::exit(self_test::run(context, portable_root.as_deref()));
+    }
+    if let Some(root) = &portable_root {
+        portable::redirect_webview_data(root);
+    }
+
     tauri::Builder::default()
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_shell::init())
-        .setup(|app| {
+        .setup(move |app| {
             app.manage(update::UpdateState {
                 progress: Arc::new(Mutex::new(UpdateProgress::default())),
             });
 
-            if cfg!(debug_assertions) {
-                app.handle().plugin(
-                    tauri_plugin_log::Builder::default()
-                        .level(log::LevelFilter::Info)
-                        .build(),
-                )?;
+            // In portable mode these all live beside the executable
+            let data_dirs = runtime::DataDirs::resolve(app.handle(), portable_root.as_deref())?;
+            data_dirs.create_all()?;
+
+            // Logged to a file in every build, so a report from a release build
+            // can be diagnosed
+            let settings = settings::SettingsState::load(&data_dirs.config);
+            let logging = settings.current().logging;
+            logging::init(app.handle(), &data_dirs.logs, &logging)?;
+            logging::prune(&data_dirs.logs, &logging);
+            for error in settings.report().errors {
+                log::warn!("Settings: {}", error);
             }
 
             #[cfg(not(any(target_os = "android", target_os = "ios")))]
@@ -116,7 +73,38 @@ pub fn run() {
 
             #[cfg(not(any(target_os = "android", target_os = "ios")))]
             app.handle().plugin(tauri_plugin_http::init())?;
-            set_global_env_vars(); // Set all paths once at startup
+
+            // Resolve and validate all paths once at startup
+            let paths = settings.current().paths;
+            let assets_dir = runtime::assets_dir(app.handle(), portable_root.as_deref());
+            if !cfg!(debug_assertions) && !data_dirs.portable {
+                migration::migrate_legacy_data(
+                    &runtime::legacy_assets_dir(),
+                    &assets_dir,
+                    &data_dirs,
+                );
+            }
+            app.manage(runtime::RuntimeState::new(&assets_dir, &data_dirs, &paths));
+            // Finding R runs Rscript; do it now, but off the main thread
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                let _ = handle.state::<runtime::RuntimeState>().config();
+            });
+            // A previous session that crashed may have left R running
+            let pid_file = data_dirs.data.join(process::PID_FILE);
+            process::clean_up_orphan(&pid_file);
+            app.manage(
+                supervisor::ShinySupervisor::new(r_shiny::app_sink(app.handle()))
+                    .with_pid_file(pid_file),
+            );
+            app.manage(app_registry::AppRegistry::new(
+                app.handle(),
+                &assets_dir,
+                &data_dirs,
+            ));
+            app.manage(lan_share::LanShareState::default());
+            app.manage(settings);
+            app.manage(data_dirs);
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -126,7 +114,32 @@ pub fn run() {
             update::get_update_progress,
             r_shiny::start_r_shiny, // Register R Shiny commands
             r_shiny::stop_r_shiny,
+            r_shiny::restart_r_shiny,
+            r_shiny::cancel_shiny_start,
+            r_shiny::quit_app,
+            supervisor::get_shiny_state,
+            app_registry::list_shiny_apps,
+            app_registry::start_shiny_app,
+            app_registry::stop_shiny_app,
+            shiny_log::get_shiny_logs,
+            logging::open_log_folder,
+            lan_share::list_share_interfaces,
+            lan_share::start_lan_share,
+            lan_share::stop_lan_share,
+            lan_share::get_lan_share,
+            r_discovery::get_r_discovery,
+            r_packages::check_r_packages,
+            runtime::get_runtime_info,
+            runtime::get_rscript_path,
+            settings::get_settings,
+            settings::set_settings,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running Tauri application");
+        .build(context)
+        .expect("error while building Tauri application")
+        .run(|app_handle, event| {
+            // However the app ends, R must not outlive it
+            if let RunEvent::ExitRequested { .. } | RunEvent::Exit = event {
+                r_shiny::stop_on_exit(app_handle);
+            }
+        });
 }
@@ -0,0 +1,146 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use crate::manifest::AppManifest;
+use crate::r_packages;
+
+/// File in the cache directory holding how long recent starts took.
+const TIMINGS_FILE: &str = "startup-timings.json";
+
+/// Number of recent starts the ETA is based on.
+const TIMINGS_KEPT: usize = 5;
+
+/// Environment variable listing the declared packages, comma-separated, so
+/// the entry script can load them one by one. `assets/start-shiny.R` is the
+/// reference for this and [`LOADED_MARKER`].
+pub const PACKAGES_VAR: &str = "RE_SHINY_PACKAGES";
+
+/// Line the entry script writes after loading each package, e.g.
+/// `message("RE_SHINY_PACKAGE_LOADED ", pkg)`. R itself prints nothing for
+/// most `library()` calls.
+pub const LOADED_MARKER: &str = "RE_SHINY_PACKAGE_LOADED";
+
+/// Payload of the `shiny-package-progress` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct LoadProgress {
+    /// Declared dependencies R has loaded so far.
+    pub loaded: usize,
+    pub total: usize,
+    /// The package R reported last, declared or not.
+    pub current_package: Option<String>,
+    /// Estimated seconds until the app is ready, once there is something to
+    /// base it on.
+    pub eta_secs: Option<u64>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Timings {
+    startup_ms: Vec<u64>,
+}
+
+fn read_timings(cache_dir: &Path) -> Timings {
+    std::fs::read_to_string(cache_dir.join(TIMINGS_FILE))
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+/// Remembers how long a start took, from spawning R until it was ready.
+pub fn record_startup(cache_dir: &Path, took: Duration) {
+    let mut timings = read_timings(cache_dir);
+    timings.startup_ms.push(took.as_millis() as u64);
+    let excess = timings.startup_ms.len().saturating_sub(TIMINGS_KEPT);
+    timings.startup_ms.drain(..excess);
+    let path = cache_dir.join(TIMINGS_FILE);
+    if let Err(e) = std::fs::write(&path, serde_json::to_string(&timings).unwrap_or_default()) {
+        log::warn!("Failed to write {}: {}", path.display(), e);
+    }
+}
+
+/// The packages the app declares: those in its manifest, or else those in
+/// its `DESCRIPTION`.
+pub fn declared_packages(app_dir: &Path) -> Vec<String> {
+    let manifest = AppManifest::load(app_dir).unwrap_or_default();
+    if !manifest.packages.is_empty() {
+        return manifest.packages.into_iter().map(|p| p.name).collect();
+    }
+    r_packages::description_dependencies(&app_dir.join("DESCRIPTION"))
+}
+
+/// Picks the package name out of the entry script's marker, or out of R's
+/// `Loading required package: x` and `Attaching package: 'x'` lines.
+fn package_in_line(line: &str) -> Option<&str> {
+    if let Some(package) = line.trim().strip_prefix(LOADED_MARKER) {
+        return Some(package.trim()).filter(|p| !p.is_empty());
+    }
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| {
+        Regex::new(r"(?:Loading required package: |Attaching package: [‘'`])([\w.]+)").unwrap()
+    });
+    pattern.captures(line)?.get(1).map(|m| m.as_str())
+}
+
+/// Follows R's output while one process loads the app's packages.
+pub struct LoadTracker {
+    declared: Vec<String>,
+    loaded: HashSet<String>,
+    started: Instant,
+    /// Mean duration of recent starts.
+    typical: Option<Duration>,
+}
+
+impl LoadTracker {
+    pub fn new(app_dir: &Path, cache_dir: &Path) -> Self {
+        let timings = read_timings(cache_dir);
+        let typical = (!timings.startup_ms.is_empty()).then(|| {
+            let total: u64 = timings.startup_ms.iter().sum();
+            Duration::from_millis(total / timings.startup_ms.len() as u64)
+        });
+        Self {
+            declared: declared_packages(app_dir),
+            loaded: HashSet::new(),
+            started: Instant::now(),
+            typical,
+        }
+    }
+
+    /// Progress once the app is ready, whatever R reported on the way.
+    pub fn finished(app_dir: &Path) -> LoadProgress {
+        let total = declared_packages(app_dir).len();
+        LoadProgress {
+            loaded: total,
+            total,
+            current_package: None,
+            eta_secs: Some(0),
+        }
+    }
+
+    /// Returns the new progress if `line` reports a package being loaded.
+    pub fn on_line(&mut self, line: &str) -> Option<LoadProgress> {
+        let package = package_in_line(line)?;
+        if self.declared.iter().any(|p| p == package) {
+            self.loaded.insert(package.to_string());
+        }
+        Some(LoadProgress {
+            loaded: self.loaded.len(),
+            total: self.declared.len(),
+            current_package: Some(package.to_string()),
+            eta_secs: self.eta().map(|eta| eta.as_secs()),
+        })
+    }
+
+    /// Based on recent starts if there were any, otherwise on the pace so
+    /// far.
+    fn eta(&self) -> Option<Duration> {
+        let elapsed = self.started.elapsed();
+        if let Some(typical) = self.typical {
+            return Some(typical.saturating_sub(elapsed));
+        }
+        let (loaded, total) = (self.loaded.len() as u32, self.declared.len() as u32);
+        (loaded > 0 && total >= loaded).then(|| elapsed / loaded * (total - loaded))
+    }
+}
@@ -0,0 +1,209 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_log::fern;
+use tauri_plugin_log::{Target, TargetKind, TimezoneStrategy};
+use time::format_description::{self, OwnedFormatItem};
+
+use crate::runtime::DataDirs;
+use crate::settings::LoggingSettings;
+
+/// Base name of the log file; rotated files get a timestamp appended.
+const LOG_FILE_NAME: &str = "grade-tool";
+
+const TIMEZONE: TimezoneStrategy = TimezoneStrategy::UseLocal;
+
+fn format(description: &str) -> OwnedFormatItem {
+    format_description::parse_owned::<1>(description).unwrap()
+}
+
+/// The log file, rotated as soon as it reaches the configured size, however
+/// long the app runs.
+struct RotatingFile {
+    dir: PathBuf,
+    settings: LoggingSettings,
+    /// Closed only while rotating, since Windows can't rename an open file.
+    file: Option<File>,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(dir: &Path, settings: &LoggingSettings) -> io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let file = Self::open_current(dir)?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            settings: settings.clone(),
+            size: file.metadata()?.len(),
+            file: Some(file),
+        })
+    }
+
+    fn current_path(dir: &Path) -> PathBuf {
+        dir.join(format!("{}.log", LOG_FILE_NAME))
+    }
+
+    fn open_current(dir: &Path) -> io::Result<File> {
+        File::options()
+            .create(true)
+            .append(true)
+            .open(Self::current_path(dir))
+    }
+
+    /// Moves the current file aside under a timestamped name and starts a
+    /// new one. Runs inside the logger, so it must not log itself.
+    fn rotate(&mut self) -> io::Result<()> {
+        let stamp = TIMEZONE
+            .get_now()
+            .format(&format("[year]-[month]-[day]_[hour]-[minute]-[second]"))
+            .map_err(io::Error::other)?;
+        let mut rotated = self.dir.join(format!("{}_{}.log", LOG_FILE_NAME, stamp));
+        let mut n = 1;
+        while rotated.exists() {
+            rotated = self
+                .dir
+                .join(format!("{}_{}_{}.log", LOG_FILE_NAME, stamp, n));
+            n += 1;
+        }
+        self.file = None;
+        let renamed = std::fs::rename(Self::current_path(&self.dir), rotated);
+        // Keep logging even if the rename failed
+        self.file = Some(Self::open_current(&self.dir)?);
+        renamed?;
+        remove_old(&self.dir, &self.settings);
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // A failed rotation may have left the file closed
+        let file = match self.file.as_mut() {
+            Some(file) => file,
+            None => self.file.insert(Self::open_current(&self.dir)?),
+        };
+        let written = file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    /// The logger flushes after each record, so rotating here never splits
+    /// one across files.
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(file) = self.file.as_mut() {
+            file.flush()?;
+        }
+        if self.size >= self.settings.max_file_size_kb * 1024 {
+            // Counted afresh even if rotation fails, e.g. while another
+            // program holds the file open, so the next attempt comes a
+            // file's worth later rather than on every record
+            self.size = 0;
+            self.rotate()?;
+        }
+        Ok(())
+    }
+}
+
+/// Logs launcher, updater and R output to the console and to a file in
+/// `dir`, in every build, and registers the log plugin. The file is rotated
+/// whenever it grows past the configured size.
+pub fn init<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    dir: &Path,
+    settings: &LoggingSettings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (plugin, max_level, stdout) = tauri_plugin_log::Builder::default()
+        .targets([Target::new(TargetKind::Stdout)])
+        .timezone_strategy(TIMEZONE)
+        .level(log::LevelFilter::Info)
+        .split(app_handle)?;
+
+    let timestamp = format("[[[year]-[month]-[day]][[[hour]:[minute]:[second]]");
+    let file: Box<dyn Write + Send> = Box::new(RotatingFile::open(dir, settings)?);
+    let logger = fern::Dispatch::new()
+        .chain(stdout)
+        .chain(
+            fern::Dispatch::new()
+                .format(move |out, message, record| {
+                    out.finish(format_args!(
+                        "{}[{}][{}] {}",
+                        TIMEZONE.get_now().format(&timestamp).unwrap_or_default(),
+                        record.level(),
+                        record.target(),
+                        message
+                    ))
+                })
+                .level(log::LevelFilter::Info)
+                .chain(file),
+        )
+        .into_log()
+        .1;
+    tauri_plugin_log::attach_logger(max_level, logger)?;
+    app_handle.plugin(plugin)?;
+    Ok(())
+}
+
+/// Deletes rotated log files older than the retention period or beyond the
+/// newest `max_files`, and logs what it removed. The current file is never
+/// touched.
+pub fn prune(dir: &Path, settings: &LoggingSettings) {
+    for (path, result) in remove_old(dir, settings) {
+        match result {
+            Ok(()) => log::info!("Removed old log file {}", path.display()),
+            Err(e) => log::warn!("Failed to remove {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// Does the work of `prune` without logging, so the logger can call it.
+fn remove_old(dir: &Path, settings: &LoggingSettings) -> Vec<(PathBuf, io::Result<()>)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let prefix = format!("{}_", LOG_FILE_NAME);
+    let mut rotated: Vec<_> = entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+        .filter_map(|entry| {
+            let modified = entry.metadata().and_then(|m| m.modified()).ok()?;
+            Some((modified, entry.path()))
+        })
+        .collect();
+    // Newest first
+    rotated.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+
+    let max_age = Duration::from_secs(settings.retention_days * 24 * 60 * 60);
+    let now = SystemTime::now();
+    rotated
+        .into_iter()
+        .enumerate()
+        .filter(|(index, (modified, _))| {
+            let expired = now.duration_since(*modified).is_ok_and(|age| age > max_age);
+            *index >= settings.max_files || expired
+        })
+        .map(|(_, (_, path))| {
+            let result = std::fs::remove_file(&path);
+            (path, result)
+        })
+        .collect()
+}
+
+/// Opens the folder holding the log files in the system file manager.
+#[tauri::command]
+pub fn open_log_folder(data_dirs: tauri::State<'_, DataDirs>) -> Result<(), String> {
+    let dir = &data_dirs.logs;
+    let program = if cfg!(target_os = "windows") {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    std::process::Command::new(program)
+        .arg(dir)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open {}: {}", dir.display(), e))
+}
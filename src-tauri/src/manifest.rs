@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::r_packages::PackageRequirement;
+use crate::r_version::RVersion;
+use crate::readiness::Readiness;
+use crate::shiny_error::ShinyStartError;
+
+/// File name of the manifest shipped inside a Shiny app directory.
+pub const MANIFEST_FILE: &str = "app.toml";
+
+/// Version and requirements declared by a Shiny app in its `app.toml`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AppManifest {
+    /// Display name for apps in the registry.
+    pub name: Option<String>,
+    /// Script Rscript runs to serve the app, relative to the app directory.
+    /// Defaults to the shared `start-shiny.R`.
+    pub entry: Option<PathBuf>,
+    pub version: Option<String>,
+    pub r: RRequirement,
+    pub packages: Vec<PackageRequirement>,
+    pub readiness: Readiness,
+}
+
+/// Supported R versions, both bounds inclusive.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RRequirement {
+    pub min_version: Option<RVersion>,
+    pub max_version: Option<RVersion>,
+}
+
+impl AppManifest {
+    /// Loads `app.toml` from `app_dir`. An app without a manifest has no
+    /// requirements.
+    pub fn load(app_dir: &Path) -> Result<Self, String> {
+        let path = Self::path(app_dir);
+        match std::fs::read_to_string(&path) {
+            Ok(text) => toml::from_str(&text)
+                .map_err(|e| format!("Invalid manifest {}: {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                log::info!("No manifest at {}, skipping checks", path.display());
+                Ok(Self::default())
+            }
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn path(app_dir: &Path) -> PathBuf {
+        app_dir.join(MANIFEST_FILE)
+    }
+}
+
+impl RRequirement {
+    /// Fails with a typed error when `found` is outside the supported range.
+    pub fn check(&self, found: RVersion) -> Result<(), ShinyStartError> {
+        let too_old = self.min_version.is_some_and(|min| found < min);
+        let too_new = self.max_version.is_some_and(|max| found > max);
+        if too_old || too_new {
+            return Err(ShinyStartError::IncompatibleRVersion {
+                found,
+                min: self.min_version,
+                max: self.max_version,
+            });
+        }
+        Ok(())
+    }
+}
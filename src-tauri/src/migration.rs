@@ -0,0 +1,110 @@
+use std::path::{Component, Path, PathBuf};
+
+use crate::r_discovery::bundled_dir_name;
+use crate::runtime::DataDirs;
+
+/// Written to the data directory once the legacy location has been handled.
+const MARKER_FILE: &str = ".legacy-assets-migrated";
+
+/// Copies user data left in the old `assets` directory next to the
+/// executable into the per-user data directories. Runs once; anything that
+/// also exists in the shipped `assets` is treated as part of the install and
+/// left alone. The old location is never modified since it may be read-only.
+pub fn migrate_legacy_data(legacy: &Path, assets: &Path, dirs: &DataDirs) {
+    let marker = dirs.data.join(MARKER_FILE);
+    if marker.exists() {
+        return;
+    }
+
+    let same_dir = match (legacy.canonicalize(), assets.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    };
+    let mut copied = 0;
+    let mut failed = Vec::new();
+
+    if legacy.is_dir() && !same_dir {
+        log::info!("Migrating user data from {}", legacy.display());
+        for file in files_under(legacy) {
+            let Ok(rel) = file.strip_prefix(legacy) else {
+                continue;
+            };
+            if assets.join(rel).exists() {
+                continue;
+            }
+            let dest = destination(rel, dirs);
+            if dest.exists() {
+                continue;
+            }
+            let result = dest
+                .parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|_| std::fs::copy(&file, &dest));
+            match result {
+                Ok(_) => copied += 1,
+                Err(e) => failed.push(format!("{}: {}", file.display(), e)),
+            }
+        }
+    }
+
+    for failure in &failed {
+        log::warn!("Failed to migrate {}", failure);
+    }
+    let summary = format!(
+        "from = {:?}\ncopied = {}\nfailed = {}\n",
+        legacy.display().to_string(),
+        copied,
+        failed.len()
+    );
+    if let Err(e) = std::fs::write(&marker, summary) {
+        log::warn!("Failed to write {}: {}", marker.display(), e);
+    }
+    if copied > 0 {
+        log::info!("Migrated {} files from {}", copied, legacy.display());
+    }
+}
+
+/// Maps a path relative to the old `assets` directory to its new home:
+/// installed packages go to the user library, files the Shiny app wrote go
+/// to its data directory, and anything else is kept under `legacy`.
+fn destination(rel: &Path, dirs: &DataDirs) -> PathBuf {
+    let parts: Vec<&Path> = rel
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => Some(Path::new(part)),
+            _ => None,
+        })
+        .collect();
+
+    match parts.as_slice() {
+        [r_dir, lib, rest @ ..]
+            if *r_dir == Path::new(bundled_dir_name()) && *lib == Path::new("library") =>
+        {
+            rest.iter()
+                .fold(dirs.user_lib.clone(), |p, part| p.join(part))
+        }
+        [shiny, rest @ ..] if *shiny == Path::new("shiny") => rest
+            .iter()
+            .fold(dirs.shiny_data.clone(), |p, part| p.join(part)),
+        _ => dirs.data.join("legacy").join(rel),
+    }
+}
+
+/// Every regular file below `root`, without following symlinks.
+fn files_under(root: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            match entry.file_type() {
+                Ok(t) if t.is_dir() => pending.push(entry.path()),
+                Ok(t) if t.is_file() => files.push(entry.path()),
+                _ => {}
+            }
+        }
+    }
+    files
+}
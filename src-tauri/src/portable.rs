@@ -0,0 +1,39 @@
+use std::path::PathBuf;
+
+/// Marker file next to the executable that turns on portable mode.
+pub const MARKER_FILE: &str = "portable.txt";
+
+/// Command-line flag that turns on portable mode.
+pub const FLAG: &str = "--portable";
+
+/// Returns the directory holding the executable when running in portable
+/// mode, i.e. when `portable.txt` sits beside it or `--portable` was passed.
+pub fn detect() -> Option<PathBuf> {
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|p| p.to_path_buf()))?;
+
+    let flagged = std::env::args().skip(1).any(|arg| arg == FLAG);
+    if flagged || exe_dir.join(MARKER_FILE).is_file() {
+        log::info!(
+            "Portable mode: keeping all data under {}",
+            exe_dir.display()
+        );
+        Some(exe_dir)
+    } else {
+        None
+    }
+}
+
+/// Points WebView2 at a folder beside the executable so the webview profile
+/// does not end up in `%LOCALAPPDATA%`. Must run before any window exists.
+#[cfg(target_os = "windows")]
+pub fn redirect_webview_data(root: &std::path::Path) {
+    std::env::set_var(
+        "WEBVIEW2_USER_DATA_FOLDER",
+        root.join("data").join("webview"),
+    );
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn redirect_webview_data(_root: &std::path::Path) {}
@@ -0,0 +1,228 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+use tokio::process::Child;
+
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+
+/// Starts the command in a process group of its own, so it and everything it
+/// spawns can be signalled together. On Linux the child also receives
+/// SIGTERM if the app dies, since the kernel delivers it when the spawning
+/// thread exits; spawn from a thread that outlives the process.
+pub fn isolate(command: &mut Command) {
+    #[cfg(unix)]
+    {
+        command.process_group(0);
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let parent = std::process::id() as libc::pid_t;
+        // SAFETY: only async-signal-safe calls between fork and exec
+        unsafe {
+            command.pre_exec(move || {
+                if libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGTERM) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                // The parent may have died before prctl took effect
+                if libc::getppid() != parent {
+                    return Err(std::io::Error::from_raw_os_error(libc::ESRCH));
+                }
+                Ok(())
+            });
+        }
+    }
+
+    // Hide the console window and keep console signals aimed at the app
+    // away from R
+    #[cfg(target_os = "windows")]
+    {
+        const CREATE_NEW_PROCESS_GROUP: u32 = 0x00000200;
+        command.creation_flags(CREATE_NO_WINDOW | CREATE_NEW_PROCESS_GROUP);
+    }
+}
+
+#[cfg(target_os = "windows")]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// Keeps a console program such as Rscript from flashing a console window
+/// on Windows. Does nothing elsewhere.
+pub fn hide_window(command: &mut Command) {
+    #[cfg(target_os = "windows")]
+    command.creation_flags(CREATE_NO_WINDOW);
+    #[cfg(not(target_os = "windows"))]
+    let _ = command;
+}
+
+/// Asks the process group to exit, waits up to `grace` for the process to
+/// do so, then kills whatever is left of the group. On Windows, taskkill
+/// without `/F` can only ask windowed processes to close and R has no
+/// window, so the group is killed right away.
+pub async fn shutdown(child: &mut Child, grace: Duration) {
+    let Some(pid) = child.id() else {
+        // Already reaped
+        return;
+    };
+    #[cfg(unix)]
+    let exited = request_exit(child, pid, grace).await;
+    #[cfg(not(unix))]
+    let exited = {
+        let _ = grace;
+        false
+    };
+
+    // Workers such as parallel clusters or pandoc may outlive R itself
+    kill_group(pid);
+    if !exited {
+        if let Err(e) = child.start_kill() {
+            log::info!("Kill failed (process may already be terminated): {}", e);
+        }
+    }
+    let _ = child.wait().await;
+}
+
+/// Sends SIGTERM to the group and waits up to `grace` for the process to
+/// exit. Returns whether it did.
+#[cfg(unix)]
+async fn request_exit(child: &mut Child, pid: u32, grace: Duration) -> bool {
+    signal_group(pid, false);
+    let exited = match tokio::time::timeout(grace, child.wait()).await {
+        Ok(Ok(_)) => true,
+        Ok(Err(e)) => {
+            log::warn!("Failed to wait for R process {}: {}", pid, e);
+            false
+        }
+        Err(_) => false,
+    };
+
+    if exited {
+        log::info!("R process {} exited after termination request", pid);
+    } else {
+        log::info!(
+            "R process {} still running after {}s, killing it",
+            pid,
+            grace.as_secs()
+        );
+    }
+    exited
+}
+
+/// Signals the group led by `pid`: SIGTERM or SIGKILL.
+#[cfg(unix)]
+fn signal_group(pid: u32, force: bool) {
+    let signal = if force { libc::SIGKILL } else { libc::SIGTERM };
+    // SAFETY: kill has no memory-safety preconditions
+    let result = unsafe { libc::kill(-(pid as libc::pid_t), signal) };
+    if result != 0 {
+        let e = std::io::Error::last_os_error();
+        if e.raw_os_error() != Some(libc::ESRCH) {
+            log::warn!("Failed to signal process group {}: {}", pid, e);
+        }
+    }
+}
+
+/// Kills the group led by `pid`, or on Windows the process tree.
+#[cfg(unix)]
+fn kill_group(pid: u32) {
+    signal_group(pid, true);
+}
+
+#[cfg(target_os = "windows")]
+fn kill_group(pid: u32) {
+    let pid = pid.to_string();
+    let mut command = Command::new("taskkill");
+    command
+        .args(["/F", "/T", "/PID", pid.as_str()])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .creation_flags(CREATE_NO_WINDOW);
+    if let Err(e) = command.status() {
+        log::warn!("Failed to run taskkill: {}", e);
+    }
+}
+
+/// Name of the file recording the running R process, in the data directory.
+pub const PID_FILE: &str = "shiny.pid";
+
+/// Contents of the PID file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PidRecord {
+    pub pid: u32,
+    /// Script R was started with, to recognise the process later.
+    pub script: PathBuf,
+}
+
+pub fn write_pid_file(path: &Path, record: &PidRecord) {
+    let result = toml::to_string(record)
+        .map_err(|e| e.to_string())
+        .and_then(|text| std::fs::write(path, text).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        log::warn!("Failed to write {}: {}", path.display(), e);
+    }
+}
+
+pub fn remove_pid_file(path: &Path) {
+    if let Err(e) = std::fs::remove_file(path) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            log::warn!("Failed to remove {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Kills an R process left running by a previous session that ended
+/// without stopping it, as recorded in the PID file at `path`. The process
+/// is only touched if it still looks like the R we started, since its PID
+/// may have been reused.
+pub fn clean_up_orphan(path: &Path) {
+    let Ok(text) = std::fs::read_to_string(path) else {
+        return;
+    };
+    match toml::from_str::<PidRecord>(&text) {
+        Ok(record) if is_our_process(&record) => {
+            log::info!(
+                "Stopping R process {} left over from a previous session",
+                record.pid
+            );
+            kill_group(record.pid);
+        }
+        Ok(_) => {}
+        Err(e) => log::warn!("Ignoring invalid {}: {}", path.display(), e),
+    }
+    remove_pid_file(path);
+}
+
+/// Whether `record.pid` is still running the recorded script.
+#[cfg(unix)]
+fn is_our_process(record: &PidRecord) -> bool {
+    Command::new("ps")
+        .args(["-p", &record.pid.to_string(), "-o", "command="])
+        .output()
+        .map(|out| String::from_utf8_lossy(&out.stdout).contains(&*record.script.to_string_lossy()))
+        .unwrap_or(false)
+}
+
+/// Whether `record.pid` is still an Rscript process. Windows offers no
+/// simple way to read another process's command line.
+#[cfg(target_os = "windows")]
+fn is_our_process(record: &PidRecord) -> bool {
+    Command::new("tasklist")
+        .args([
+            "/FI",
+            &format!("PID eq {}", record.pid),
+            "/FO",
+            "CSV",
+            "/NH",
+        ])
+        .creation_flags(0x08000000)
+        .output()
+        .map(|out| {
+            String::from_utf8_lossy(&out.stdout)
+                .to_ascii_lowercase()
+                .contains("\"rscript.exe\"")
+        })
+        .unwrap_or(false)
+}
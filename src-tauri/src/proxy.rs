@@ -0,0 +1,382 @@
+use http_body_util::{combinators::BoxBody, BodyExt, Full};
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{self, HeaderValue};
+use hyper::service::service_fn;
+use hyper::{Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use rand::RngCore;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+
+/// Query parameter carrying the access token in a share URL.
+const TOKEN_PARAM: &str = "token";
+
+/// Header carrying the upstream secret on every forwarded request.
+pub const SECRET_HEADER: &str = "x-re-shiny-secret";
+
+/// Environment variable passing the upstream secret to R, whose entry
+/// script refuses requests without it in [`SECRET_HEADER`]. Other local
+/// users can reach R's loopback port, but not past that check.
+pub const SECRET_VAR: &str = "RE_SHINY_SECRET";
+
+type ProxyBody = BoxBody<Bytes, hyper::Error>;
+
+/// Where the app is served and the secret it expects.
+pub struct Target {
+    pub addr: SocketAddr,
+    pub secret: String,
+}
+
+/// Looks up the app's [`Target`], or `None` while it is not running. Asked
+/// on every request, so the proxy follows restarts.
+pub type Upstream = Arc<dyn Fn() -> Option<Target> + Send + Sync>;
+
+/// A random token for one proxy, hex encoded.
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Compares tokens in constant time, so a guess can't be refined by timing.
+fn token_matches(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+struct Shared {
+    token: String,
+    /// Cookies aren't scoped by port, so the name includes it to keep
+    /// proxies on the same host apart.
+    cookie: String,
+    upstream: Upstream,
+}
+
+/// An HTTP and WebSocket reverse proxy that only forwards requests carrying
+/// its token, either as `?token=` or, once that URL was opened, as a cookie.
+/// Stops, closing every connection, when dropped.
+pub struct Proxy {
+    addr: SocketAddr,
+    token: String,
+    _shutdown: watch::Sender<()>,
+}
+
+impl Proxy {
+    /// Serves on `listener`, which the caller has bound, and forwards
+    /// authenticated traffic to `upstream`. Must be called from within the
+    /// async runtime.
+    pub fn serve(listener: std::net::TcpListener, upstream: Upstream) -> std::io::Result<Self> {
+        listener.set_nonblocking(true)?;
+        let listener = TcpListener::from_std(listener)?;
+        let addr = listener.local_addr()?;
+        let token = generate_token();
+        let shared = Arc::new(Shared {
+            token: token.clone(),
+            cookie: format!("re_shiny_token_{}", addr.port()),
+            upstream,
+        });
+        let (shutdown, closed) = watch::channel(());
+
+        tauri::async_runtime::spawn(async move {
+            let mut stop = closed.clone();
+            loop {
+                let (stream, _) = tokio::select! {
+                    accepted = listener.accept() => match accepted {
+                        Ok(accepted) => accepted,
+                        Err(e) => {
+                            log::warn!("Proxy on {} failed to accept: {}", addr, e);
+                            continue;
+                        }
+                    },
+                    _ = stop.changed() => break,
+                };
+                tokio::spawn(serve_connection(stream, shared.clone(), closed.clone()));
+            }
+            log::info!("Proxy on {} stopped", addr);
+        });
+        log::info!("Proxy listening on {}", addr);
+
+        Ok(Self {
+            addr,
+            token,
+            _shutdown: shutdown,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The URL to open, with the token, for the proxy reached at `host`.
+    pub fn url(&self, host: &str) -> String {
+        let host = if host.contains(':') {
+            format!("[{}]", host)
+        } else {
+            host.to_string()
+        };
+        format!(
+            "http://{}:{}/?{}={}",
+            host,
+            self.addr.port(),
+            TOKEN_PARAM,
+            self.token
+        )
+    }
+}
+
+/// Serves one client connection until it closes or the proxy stops.
+async fn serve_connection(stream: TcpStream, shared: Arc<Shared>, mut closed: watch::Receiver<()>) {
+    let tunnels = closed.clone();
+    let service = service_fn(move |request| handle(request, shared.clone(), tunnels.clone()));
+    let connection = hyper::server::conn::http1::Builder::new()
+        .serve_connection(TokioIo::new(stream), service)
+        .with_upgrades();
+    tokio::select! {
+        result = connection => {
+            if let Err(e) = result {
+                log::warn!("Proxy connection failed: {}", e);
+            }
+        }
+        _ = closed.changed() => {}
+    }
+}
+
+fn text_response(status: StatusCode, text: &'static str) -> Response<ProxyBody> {
+    let mut response = Response::new(
+        Full::new(Bytes::from_static(text.as_bytes()))
+            .map_err(|never| match never {})
+            .boxed(),
+    );
+    *response.status_mut() = status;
+    response
+}
+
+/// Where to send the browser once the token is traded for a cookie: the
+/// same path and remaining query, always on this host. Leading slashes are
+/// collapsed, since `//host/` would leave it.
+fn redirect_location(path: &str, rest: &[&str]) -> String {
+    let path = format!("/{}", path.trim_start_matches(['/', '\\']));
+    if rest.is_empty() {
+        path
+    } else {
+        format!("{}?{}", path, rest.join("&"))
+    }
+}
+
+/// Splits the token out of a query string, returning it and the rest.
+fn take_token(query: &str) -> (Option<&str>, Vec<&str>) {
+    let mut token = None;
+    let mut rest = Vec::new();
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        match pair
+            .strip_prefix(TOKEN_PARAM)
+            .and_then(|p| p.strip_prefix('='))
+        {
+            Some(value) => token = Some(value),
+            None => rest.push(pair),
+        }
+    }
+    (token, rest)
+}
+
+/// Removes our cookie from the request and returns its value, so the app
+/// never sees the token.
+fn take_cookie<B>(request: &mut Request<B>, name: &str) -> Option<String> {
+    let mut found = None;
+    let mut rest = Vec::new();
+    for value in request.headers().get_all(header::COOKIE) {
+        for cookie in value.to_str().unwrap_or_default().split(';') {
+            let cookie = cookie.trim();
+            match cookie.split_once('=') {
+                Some((key, value)) if key == name => found = Some(value.to_string()),
+                _ if !cookie.is_empty() => rest.push(cookie.to_string()),
+                _ => {}
+            }
+        }
+    }
+    request.headers_mut().remove(header::COOKIE);
+    if !rest.is_empty() {
+        if let Ok(value) = HeaderValue::from_str(&rest.join("; ")) {
+            request.headers_mut().insert(header::COOKIE, value);
+        }
+    }
+    found
+}
+
+async fn handle(
+    mut request: Request<Incoming>,
+    shared: Arc<Shared>,
+    closed: watch::Receiver<()>,
+) -> Result<Response<ProxyBody>, Infallible> {
+    // Opening the URL with the token trades it for a cookie and redirects to
+    // the same page without it, so it doesn't linger in the address bar
+    let query = request.uri().query().unwrap_or_default();
+    if let (Some(token), rest) = take_token(query) {
+        if !token_matches(token, &shared.token) {
+            return Ok(text_response(StatusCode::FORBIDDEN, "Invalid access token"));
+        }
+        let location = redirect_location(request.uri().path(), &rest);
+        let cookie = format!(
+            "{}={}; Path=/; HttpOnly; SameSite=Strict",
+            shared.cookie, shared.token
+        );
+        let mut response = text_response(StatusCode::SEE_OTHER, "");
+        if let (Ok(location), Ok(cookie)) = (
+            HeaderValue::from_str(&location),
+            HeaderValue::from_str(&cookie),
+        ) {
+            response.headers_mut().insert(header::LOCATION, location);
+            response.headers_mut().insert(header::SET_COOKIE, cookie);
+        }
+        return Ok(response);
+    }
+
+    let authenticated = take_cookie(&mut request, &shared.cookie)
+        .is_some_and(|token| token_matches(&token, &shared.token));
+    if !authenticated {
+        return Ok(text_response(
+            StatusCode::FORBIDDEN,
+            "Open the link you were given to access this app",
+        ));
+    }
+
+    match forward(request, &shared, closed).await {
+        Ok(response) => Ok(response),
+        Err(e) => {
+            log::warn!("Proxy failed to reach the app: {}", e);
+            Ok(text_response(
+                StatusCode::BAD_GATEWAY,
+                "The app is not available right now",
+            ))
+        }
+    }
+}
+
+/// Sends an authenticated request to the app and returns its response. An
+/// upgrade such as a WebSocket is tunnelled in both directions afterwards.
+async fn forward(
+    mut request: Request<Incoming>,
+    shared: &Shared,
+    mut closed: watch::Receiver<()>,
+) -> Result<Response<ProxyBody>, String> {
+    let target = (shared.upstream)().ok_or("the app is not running")?;
+    let secret = HeaderValue::from_str(&target.secret).map_err(|e| e.to_string())?;
+    request.headers_mut().insert(SECRET_HEADER, secret);
+    let stream = TcpStream::connect(target.addr)
+        .await
+        .map_err(|e| format!("failed to connect to {}: {}", target.addr, e))?;
+    let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+        .await
+        .map_err(|e| e.to_string())?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.with_upgrades().await {
+            log::warn!("Proxy upstream connection failed: {}", e);
+        }
+    });
+
+    let wants_upgrade = request.headers().contains_key(header::UPGRADE);
+    let client_upgrade = wants_upgrade.then(|| hyper::upgrade::on(&mut request));
+    let mut response = sender
+        .send_request(request)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if let (StatusCode::SWITCHING_PROTOCOLS, Some(client_upgrade)) =
+        (response.status(), client_upgrade)
+    {
+        let upstream_upgrade = hyper::upgrade::on(&mut response);
+        tokio::spawn(async move {
+            let (client, upstream) = match tokio::try_join!(client_upgrade, upstream_upgrade) {
+                Ok(upgraded) => upgraded,
+                Err(e) => {
+                    log::warn!("Proxy upgrade failed: {}", e);
+                    return;
+                }
+            };
+            let mut client = TokioIo::new(client);
+            let mut upstream = TokioIo::new(upstream);
+            tokio::select! {
+                _ = tokio::io::copy_bidirectional(&mut client, &mut upstream) => {}
+                _ = closed.changed() => {}
+            }
+        });
+    }
+    Ok(response.map(|body| body.boxed()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request_with_cookies(cookies: &[&str]) -> Request<()> {
+        let mut builder = Request::builder().uri("/");
+        for cookie in cookies {
+            builder = builder.header(header::COOKIE, *cookie);
+        }
+        builder.body(()).unwrap()
+    }
+
+    #[test]
+    fn takes_the_token_and_keeps_other_params() {
+        let (token, rest) = take_token("tab=grades&token=abc&sort=asc");
+        assert_eq!(token, Some("abc"));
+        assert_eq!(rest, ["tab=grades", "sort=asc"]);
+    }
+
+    #[test]
+    fn query_without_a_token() {
+        let (token, rest) = take_token("tab=grades&tokens=abc");
+        assert_eq!(token, None);
+        assert_eq!(rest, ["tab=grades", "tokens=abc"]);
+        assert_eq!(take_token(""), (None, Vec::new()));
+    }
+
+    #[test]
+    fn tokens_must_match_exactly() {
+        assert!(token_matches("abc123", "abc123"));
+        assert!(!token_matches("abc124", "abc123"));
+        assert!(!token_matches("abc12", "abc123"));
+        assert!(!token_matches("", "abc123"));
+    }
+
+    #[test]
+    fn strips_the_token_cookie_before_forwarding() {
+        let mut request =
+            request_with_cookies(&["theme=dark; re_shiny_token_3000=secret", "lang=en"]);
+        let token = take_cookie(&mut request, "re_shiny_token_3000");
+        assert_eq!(token.as_deref(), Some("secret"));
+        let cookies: Vec<_> = request.headers().get_all(header::COOKIE).iter().collect();
+        assert_eq!(cookies, ["theme=dark; lang=en"]);
+    }
+
+    #[test]
+    fn removes_the_cookie_header_when_only_the_token_was_sent() {
+        let mut request = request_with_cookies(&["re_shiny_token_3000=secret"]);
+        assert!(take_cookie(&mut request, "re_shiny_token_3000").is_some());
+        assert!(!request.headers().contains_key(header::COOKIE));
+    }
+
+    #[test]
+    fn ignores_cookies_of_other_proxies() {
+        let mut request = request_with_cookies(&["re_shiny_token_3001=other"]);
+        assert_eq!(take_cookie(&mut request, "re_shiny_token_3000"), None);
+        let cookies: Vec<_> = request.headers().get_all(header::COOKIE).iter().collect();
+        assert_eq!(cookies, ["re_shiny_token_3001=other"]);
+    }
+
+    #[test]
+    fn redirects_stay_on_this_host() {
+        assert_eq!(redirect_location("/", &[]), "/");
+        assert_eq!(redirect_location("/app", &["tab=1"]), "/app?tab=1");
+        assert_eq!(redirect_location("//evil.example/", &[]), "/evil.example/");
+        assert_eq!(redirect_location("/\\evil.example", &[]), "/evil.example");
+        assert_eq!(redirect_location("", &[]), "/");
+    }
+}
@@ -0,0 +1,294 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::process;
+
+/// Where an R installation was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RSource {
+    Settings,
+    Bundled,
+    RHomeEnv,
+    Path,
+    SystemLocation,
+}
+
+/// An R installation with a usable `Rscript`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RInstallation {
+    pub r_home: PathBuf,
+    pub rscript: PathBuf,
+    pub source: RSource,
+    pub reason: String,
+}
+
+/// A location that was checked during discovery and what was found there.
+#[derive(Debug, Clone, Serialize)]
+pub struct RCandidate {
+    pub path: PathBuf,
+    pub source: RSource,
+    pub note: String,
+}
+
+/// Outcome of R discovery: the selected installation plus every candidate
+/// that was considered, in the order they were tried.
+#[derive(Debug, Clone, Serialize)]
+pub struct RDiscovery {
+    pub selected: Option<RInstallation>,
+    pub candidates: Vec<RCandidate>,
+}
+
+#[cfg(target_os = "windows")]
+const RSCRIPT_NAME: &str = "Rscript.exe";
+#[cfg(not(target_os = "windows"))]
+const RSCRIPT_NAME: &str = "Rscript";
+
+/// Name of the bundled R directory under `assets` for this platform.
+pub fn bundled_dir_name() -> &'static str {
+    if cfg!(target_os = "windows") {
+        "r-win"
+    } else if cfg!(target_os = "macos") {
+        "r-mac"
+    } else {
+        "r-linux"
+    }
+}
+
+/// Finds an R installation, trying a configured R home first, then the
+/// bundled runtime, `R_HOME`, `PATH`, and finally the usual install locations
+/// for the platform.
+pub fn discover(assets_dir: &Path, configured_home: Option<&Path>) -> RDiscovery {
+    let mut discovery = RDiscovery {
+        selected: None,
+        candidates: Vec::new(),
+    };
+
+    if let Some(r_home) = configured_home {
+        if discovery.try_home(r_home, RSource::Settings, "configured in settings") {
+            return discovery;
+        }
+    }
+
+    let bundled = assets_dir.join(bundled_dir_name());
+    if discovery.try_home(&bundled, RSource::Bundled, "bundled with the application") {
+        return discovery;
+    }
+
+    if let Some(r_home) = std::env::var_os("R_HOME").filter(|v| !v.is_empty()) {
+        if discovery.try_home(Path::new(&r_home), RSource::RHomeEnv, "R_HOME is set") {
+            return discovery;
+        }
+    }
+
+    if let Some(rscript) = find_on_path() {
+        let r_home = query_r_home(&rscript).unwrap_or_else(|| home_from_rscript(&rscript));
+        discovery.select(RInstallation {
+            reason: format!("found {} on PATH", rscript.display()),
+            r_home,
+            rscript,
+            source: RSource::Path,
+        });
+        return discovery;
+    }
+
+    for home in system_locations() {
+        if discovery.try_home(&home, RSource::SystemLocation, "standard install location") {
+            return discovery;
+        }
+    }
+
+    discovery
+}
+
+impl RDiscovery {
+    /// Records `r_home` as a candidate and selects it if it has an Rscript.
+    fn try_home(&mut self, r_home: &Path, source: RSource, why: &str) -> bool {
+        match rscript_in_home(r_home) {
+            Some(rscript) => {
+                self.select(RInstallation {
+                    r_home: r_home.to_path_buf(),
+                    rscript,
+                    source,
+                    reason: why.to_string(),
+                });
+                true
+            }
+            None => {
+                self.candidates.push(RCandidate {
+                    path: r_home.to_path_buf(),
+                    source,
+                    note: if r_home.exists() {
+                        format!("no {} under bin", RSCRIPT_NAME)
+                    } else {
+                        "not found".to_string()
+                    },
+                });
+                false
+            }
+        }
+    }
+
+    fn select(&mut self, installation: RInstallation) {
+        log::info!(
+            "Selected R at {} ({})",
+            installation.r_home.display(),
+            installation.reason
+        );
+        self.candidates.push(RCandidate {
+            path: installation.r_home.clone(),
+            source: installation.source,
+            note: "selected".to_string(),
+        });
+        self.selected = Some(installation);
+    }
+}
+
+fn rscript_in_home(r_home: &Path) -> Option<PathBuf> {
+    let rscript = r_home.join("bin").join(RSCRIPT_NAME);
+    rscript.is_file().then_some(rscript)
+}
+
+fn find_on_path() -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(RSCRIPT_NAME))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Asks R itself where its home is; PATH entries are often wrapper scripts
+/// that live outside the R home.
+fn query_r_home(rscript: &Path) -> Option<PathBuf> {
+    let mut command = Command::new(rscript);
+    command.args(["--vanilla", "-e", "cat(R.home())"]);
+    process::hide_window(&mut command);
+    let output = command.output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let home = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!home.is_empty()).then(|| PathBuf::from(home))
+}
+
+/// Asks R for its site libraries and base library. Distributions install
+/// packages such as shiny into site libraries outside the R home, e.g.
+/// `/usr/lib/R/site-library` on Debian, and only R knows where they are.
+/// Library variables from the parent are dropped so only R's own defaults
+/// are reported.
+pub fn query_libraries(rscript: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut command = Command::new(rscript);
+    command
+        .args([
+            "--vanilla",
+            "-e",
+            "cat(.Library.site, .Library, sep = '\\n')",
+        ])
+        .env_remove("R_LIBS")
+        .env_remove("R_LIBS_USER")
+        .env_remove("R_LIBS_SITE");
+    process::hide_window(&mut command);
+    let output = command
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", rscript.display(), e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} exited with {}: {}",
+            rscript.display(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+fn home_from_rscript(rscript: &Path) -> PathBuf {
+    let resolved = std::fs::canonicalize(rscript).unwrap_or_else(|_| rscript.to_path_buf());
+    resolved
+        .parent()
+        .and_then(|bin| bin.parent())
+        .map(|p| p.to_path_buf())
+        .unwrap_or_default()
+}
+
+/// Subdirectories of `dir`, newest version name first.
+fn versioned_subdirs(dir: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.is_dir())
+                .collect()
+        })
+        .unwrap_or_default();
+    dirs.sort_by_key(|p| version_key(p));
+    dirs.reverse();
+    dirs
+}
+
+/// Sort key that orders `4.10.0` after `4.9.1`; non-numeric parts sort first.
+fn version_key(path: &Path) -> Vec<u32> {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default()
+        .split(|c: char| !c.is_ascii_digit())
+        .filter_map(|part| part.parse().ok())
+        .collect()
+}
+
+#[cfg(target_os = "windows")]
+fn system_locations() -> Vec<PathBuf> {
+    let mut homes = Vec::new();
+    for var in ["ProgramFiles", "LOCALAPPDATA"] {
+        if let Some(base) = std::env::var_os(var) {
+            let base = PathBuf::from(base);
+            homes.extend(versioned_subdirs(&base.join("R")));
+            homes.extend(versioned_subdirs(&base.join("Programs").join("R")));
+        }
+    }
+    homes
+}
+
+#[cfg(target_os = "macos")]
+fn system_locations() -> Vec<PathBuf> {
+    // rig installs each version under Versions/<x.y>; Current is the default
+    let framework = PathBuf::from("/Library/Frameworks/R.framework");
+    let mut homes = vec![framework.join("Resources")];
+    homes.extend(
+        versioned_subdirs(&framework.join("Versions"))
+            .into_iter()
+            .map(|v| v.join("Resources")),
+    );
+    homes.push(PathBuf::from("/opt/homebrew/lib/R"));
+    homes.push(PathBuf::from("/usr/local/lib/R"));
+    homes
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn system_locations() -> Vec<PathBuf> {
+    let mut homes = vec![
+        PathBuf::from("/usr/lib/R"),
+        PathBuf::from("/usr/local/lib/R"),
+    ];
+    // /opt/R/<version> is used by both rig and the Posit R builds
+    homes.extend(
+        versioned_subdirs(Path::new("/opt/R"))
+            .into_iter()
+            .map(|v| v.join("lib").join("R")),
+    );
+    homes
+}
+
+/// Returns how R was discovered at startup.
+#[tauri::command]
+pub async fn get_r_discovery(
+    runtime: tauri::State<'_, crate::runtime::RuntimeState>,
+) -> Result<RDiscovery, String> {
+    Ok(runtime.discovery().clone())
+}
@@ -0,0 +1,141 @@
+use std::collections::BTreeMap;
+use std::ffi::{OsStr, OsString};
+use std::process::Command;
+
+use crate::runtime::RuntimeConfig;
+use crate::settings::REnvSettings;
+
+/// Variables the OS or R itself needs to function; always passed through.
+#[cfg(target_os = "windows")]
+const ESSENTIAL_VARS: &[&str] = &[
+    "PATH",
+    "PATHEXT",
+    "SystemRoot",
+    "SystemDrive",
+    "windir",
+    "COMSPEC",
+    "USERNAME",
+    "USERPROFILE",
+    "APPDATA",
+    "LOCALAPPDATA",
+    "PROGRAMDATA",
+    "NUMBER_OF_PROCESSORS",
+    "PROCESSOR_ARCHITECTURE",
+    "OS",
+];
+
+#[cfg(not(target_os = "windows"))]
+const ESSENTIAL_VARS: &[&str] = &["PATH", "USER", "LOGNAME", "SHELL", "TZ"];
+
+/// Locale variables passed through unless `r_env.locale` pins the locale.
+const LOCALE_VARS: &[&str] = &[
+    "LANG",
+    "LANGUAGE",
+    "LC_ALL",
+    "LC_COLLATE",
+    "LC_CTYPE",
+    "LC_MESSAGES",
+    "LC_MONETARY",
+    "LC_NUMERIC",
+    "LC_TIME",
+];
+
+#[cfg(target_os = "windows")]
+const NULL_DEVICE: &str = "NUL";
+#[cfg(not(target_os = "windows"))]
+const NULL_DEVICE: &str = "/dev/null";
+
+fn allowed(name: &OsStr, allowlist: &[&str]) -> bool {
+    let Some(name) = name.to_str() else {
+        return false;
+    };
+    // Windows variable names are case-insensitive
+    if cfg!(target_os = "windows") {
+        allowlist.iter().any(|a| a.eq_ignore_ascii_case(name))
+    } else {
+        allowlist.contains(&name)
+    }
+}
+
+/// Builds the complete environment for an Rscript child from a clean slate:
+/// allowlisted parent variables, then everything R needs pinned explicitly
+/// so user profiles, a conda-activated R or a stray `R_LIBS_USER` cannot
+/// change which packages load. The locale is the system's unless
+/// configured.
+pub fn build(
+    config: &RuntimeConfig,
+    settings: &REnvSettings,
+) -> Result<BTreeMap<OsString, OsString>, String> {
+    let locale_vars = if settings.locale.is_none() {
+        LOCALE_VARS
+    } else {
+        &[]
+    };
+    let allowlist: Vec<&str> = ESSENTIAL_VARS
+        .iter()
+        .chain(locale_vars)
+        .copied()
+        .chain(settings.inherit.iter().map(|s| s.as_str()))
+        .collect();
+    let mut env: BTreeMap<OsString, OsString> = std::env::vars_os()
+        .filter(|(name, _)| allowed(name, &allowlist))
+        .collect();
+
+    let mut set = |name: &str, value: &OsStr| {
+        env.insert(name.into(), value.to_os_string());
+    };
+
+    set("R_HOME", config.r_home.as_os_str());
+    set("RHOME", config.r_home.as_os_str());
+    set("R_HOME_DIR", config.r_home.as_os_str());
+
+    // The user's library comes first so packages they install win
+    let r_libs = std::env::join_paths(config.library_paths())
+        .map_err(|e| format!("Invalid R library path: {}", e))?;
+    set("R_LIBS", &r_libs);
+    set("R_LIB_PATHS", &r_libs);
+    set("R_LIBS_USER", config.data.user_lib.as_os_str());
+    let site_libs = std::env::join_paths(&config.site_libs)
+        .map_err(|e| format!("Invalid R library path: {}", e))?;
+    set("R_LIBS_SITE", &site_libs);
+
+    // Startup files are never read, whatever the user has configured
+    let null = OsStr::new(NULL_DEVICE);
+    set("R_ENVIRON", null);
+    set("R_ENVIRON_USER", null);
+    set("R_PROFILE", null);
+    set("R_PROFILE_USER", null);
+
+    let tmp = config.data.cache.join("r-tmp");
+    std::fs::create_dir_all(&tmp)
+        .map_err(|e| format!("Failed to create {}: {}", tmp.display(), e))?;
+    set("TMPDIR", tmp.as_os_str());
+    set("TMP", tmp.as_os_str());
+    set("TEMP", tmp.as_os_str());
+
+    if let Some(locale) = &settings.locale {
+        set("LANG", OsStr::new(locale));
+        set("LC_ALL", OsStr::new(locale));
+    }
+
+    // `~` inside R resolves to the app's data, not the real profile
+    set("HOME", config.data.shiny_data.as_os_str());
+    set("R_USER", config.data.shiny_data.as_os_str());
+
+    set("RE_SHINY_PATH", config.shiny_app.as_os_str());
+    set("RE_SHINY_DATA_DIR", config.data.shiny_data.as_os_str());
+    set("RE_CACHE_DIR", config.data.cache.as_os_str());
+
+    Ok(env)
+}
+
+/// Replaces the command's environment with `env` and logs it. Values may
+/// hold inherited secrets, so only the names are logged at info level.
+pub fn apply(command: &mut Command, env: &BTreeMap<OsString, OsString>) {
+    let names: Vec<_> = env.keys().map(|name| name.to_string_lossy()).collect();
+    log::info!("R process environment: {}", names.join(", "));
+    for (name, value) in env {
+        log::debug!("  {} = {}", name.to_string_lossy(), value.to_string_lossy());
+    }
+    command.env_clear().envs(env);
+}
@@ -0,0 +1,241 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::manifest::AppManifest;
+use crate::runtime::RuntimeState;
+
+/// An R package version such as `1.7.4.1` or `0.3-2`. Missing components
+/// count as zero, so `1.0` and `1.0.0` are equal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PackageVersion(Vec<u32>);
+
+impl PackageVersion {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        text.trim()
+            .split(['.', '-'])
+            .map(|part| part.parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map(Self)
+            .map_err(|_| format!("'{}' is not a valid package version", text.trim()))
+    }
+}
+
+impl Ord for PackageVersion {
+    /// Compares component-wise, treating missing components as zero.
+    fn cmp(&self, other: &Self) -> Ordering {
+        let len = self.0.len().max(other.0.len());
+        (0..len)
+            .map(|i| {
+                let a = self.0.get(i).copied().unwrap_or(0);
+                let b = other.0.get(i).copied().unwrap_or(0);
+                a.cmp(&b)
+            })
+            .find(|ord| ord.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialEq for PackageVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for PackageVersion {}
+
+impl PartialOrd for PackageVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for PackageVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self.0.iter().map(|n| n.to_string()).collect();
+        f.write_str(&parts.join("."))
+    }
+}
+
+impl TryFrom<String> for PackageVersion {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::parse(&value)
+    }
+}
+
+impl From<PackageVersion> for String {
+    fn from(value: PackageVersion) -> Self {
+        value.to_string()
+    }
+}
+
+/// A package the Shiny app needs, as declared in its manifest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageRequirement {
+    pub name: String,
+    pub min_version: Option<PackageVersion>,
+}
+
+/// A package that is installed but older than required.
+#[derive(Debug, Clone, Serialize)]
+pub struct OutdatedPackage {
+    pub name: String,
+    pub installed: PackageVersion,
+    pub required: PackageVersion,
+}
+
+/// Result of comparing the installed libraries with the app's requirements.
+#[derive(Debug, Clone, Serialize)]
+pub struct PackageReport {
+    pub libraries: Vec<PathBuf>,
+    pub missing: Vec<PackageRequirement>,
+    pub outdated: Vec<OutdatedPackage>,
+    pub satisfied: Vec<String>,
+}
+
+impl PackageReport {
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.outdated.is_empty()
+    }
+}
+
+/// Reads the `Version:` field from a package's `DESCRIPTION` file.
+fn installed_version(library: &Path, package: &str) -> Option<PackageVersion> {
+    let text = std::fs::read_to_string(library.join(package).join("DESCRIPTION")).ok()?;
+    text.lines()
+        .find_map(|line| line.strip_prefix("Version:"))
+        .and_then(|v| PackageVersion::parse(v).ok())
+}
+
+/// Packages named in the `Depends` and `Imports` fields of a `DESCRIPTION`
+/// file, without version constraints and without R itself.
+pub fn description_dependencies(path: &Path) -> Vec<String> {
+    std::fs::read_to_string(path)
+        .map(|text| dependencies_in(&text))
+        .unwrap_or_default()
+}
+
+fn dependencies_in(text: &str) -> Vec<String> {
+    // Fields may continue on indented lines
+    let mut fields: Vec<String> = Vec::new();
+    for line in text.lines() {
+        match fields.last_mut() {
+            Some(field) if line.starts_with([' ', '\t']) => field.push_str(line),
+            _ => fields.push(line.to_string()),
+        }
+    }
+    fields
+        .iter()
+        .filter_map(|field| {
+            field
+                .strip_prefix("Depends:")
+                .or_else(|| field.strip_prefix("Imports:"))
+        })
+        .flat_map(|value| value.split(','))
+        .map(|entry| {
+            entry
+                .split('(')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        })
+        .filter(|name| !name.is_empty() && name != "R")
+        .collect()
+}
+
+/// Checks `required` against the first library, in order, that has each
+/// package installed.
+pub fn check(libraries: &[PathBuf], required: &[PackageRequirement]) -> PackageReport {
+    let mut report = PackageReport {
+        libraries: libraries.to_vec(),
+        missing: Vec::new(),
+        outdated: Vec::new(),
+        satisfied: Vec::new(),
+    };
+
+    for requirement in required {
+        let installed = libraries
+            .iter()
+            .find_map(|lib| installed_version(lib, &requirement.name));
+        match (installed, &requirement.min_version) {
+            (None, _) => report.missing.push(requirement.clone()),
+            (Some(installed), Some(min)) if installed < *min => {
+                report.outdated.push(OutdatedPackage {
+                    name: requirement.name.clone(),
+                    installed,
+                    required: min.clone(),
+                })
+            }
+            (Some(_), _) => report.satisfied.push(requirement.name.clone()),
+        }
+    }
+    report
+}
+
+/// Reports missing and outdated R packages for the Shiny app.
+#[tauri::command]
+pub async fn check_r_packages(
+    runtime: tauri::State<'_, RuntimeState>,
+) -> Result<PackageReport, String> {
+    let config = runtime.config()?;
+    let manifest = AppManifest::load(&config.shiny_app)?;
+    Ok(check(&config.library_paths(), &manifest.packages))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(text: &str) -> PackageVersion {
+        PackageVersion::parse(text).unwrap()
+    }
+
+    #[test]
+    fn parses_dot_and_dash_separators() {
+        assert_eq!(version("1.7.4.1").0, [1, 7, 4, 1]);
+        assert_eq!(version(" 0.3-2\n").0, [0, 3, 2]);
+        assert!(PackageVersion::parse("1.x").is_err());
+        assert!(PackageVersion::parse("").is_err());
+    }
+
+    #[test]
+    fn missing_components_count_as_zero() {
+        assert_eq!(version("1.0"), version("1.0.0"));
+        assert_eq!(version("1.0").cmp(&version("1.0.0")), Ordering::Equal);
+        assert!(version("1.0") < version("1.0.1"));
+    }
+
+    #[test]
+    fn compares_numerically() {
+        assert!(version("1.10.0") > version("1.9.9"));
+        assert!(version("0.3-2") < version("0.3.10"));
+        assert_eq!(version("0.3-2"), version("0.3.2"));
+    }
+
+    #[test]
+    fn dependencies_drop_version_constraints_and_r() {
+        let text = "Package: gradebook\n\
+                    Depends: R (>= 4.1.0), methods\n\
+                    Imports: shiny (>= 1.7.4), DT(>= 0.28),readxl\n\
+                    Suggests: testthat\n";
+        assert_eq!(dependencies_in(text), ["methods", "shiny", "DT", "readxl"]);
+    }
+
+    #[test]
+    fn dependencies_follow_continuation_lines() {
+        let text = "Package: gradebook\n\
+                    Imports:\n    shiny (>= 1.7.4),\n\tbslib,\n    DT\n\
+                    License: MIT\n";
+        assert_eq!(dependencies_in(text), ["shiny", "bslib", "DT"]);
+    }
+
+    #[test]
+    fn dependencies_of_a_missing_file_are_empty() {
+        assert!(description_dependencies(Path::new("/definitely/not/DESCRIPTION")).is_empty());
+    }
+}
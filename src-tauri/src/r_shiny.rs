@@ -1,288 +1,730 @@
-use lazy_static::lazy_static;
-use reqwest::blocking::Client;
-use std::net::{TcpListener, TcpStream};
-#[cfg(target_os = "windows")]
-use std::os::windows::process::CommandExt;
-use std::process::{Child, Command, Stdio};
-use std::time::Duration;
-use std::{env, sync::Mutex, thread, time};
-use tauri::Emitter;
-use tauri_plugin_shell::process::CommandEvent;
-use tauri_plugin_shell::ShellExt;
-
-lazy_static! {
-    static ref R_PROCESS: Mutex<Option<Child>> = Mutex::new(None);
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use tokio::time::sleep;
+
+use crate::app_registry::AppRegistry;
+use crate::diagnosis;
+use crate::handshake::{self, BoundAddress, ADDRESS_FILE_VAR};
+use crate::lan_share;
+use crate::load_progress::{self, LoadTracker};
+use crate::manifest::AppManifest;
+use crate::process;
+use crate::proxy::{self, Proxy};
+use crate::r_env;
+use crate::r_packages;
+use crate::r_version::{self, RVersion};
+use crate::readiness::{ReadyProbe, StartupPhase, READY_FILE_VAR};
+use crate::runtime::{RuntimeConfig, RuntimeState};
+use crate::settings::{LauncherSettings, Settings, SettingsState};
+use crate::shiny_error::ShinyStartError;
+use crate::shiny_log::LogStream;
+use crate::supervisor::{ShinyState, ShinySupervisor};
+
+/// How often a running process is checked for an unexpected exit.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Reserves the first free port in `start..end` by binding it. The listener
+/// is held until just before R is spawned.
+fn reserve_port(host: &str, start: u16, end: u16) -> Option<(u16, TcpListener)> {
+    (start..end).find_map(|port| {
+        TcpListener::bind((host, port))
+            .ok()
+            .map(|listener| (port, listener))
+    })
+}
+
+/// Reserves a port for R chosen by the system.
+fn reserve_private_port(host: &str) -> Option<(u16, TcpListener)> {
+    let listener = TcpListener::bind((host, 0)).ok()?;
+    Some((listener.local_addr().ok()?.port(), listener))
+}
+
+/// Where R reports the address it actually bound.
+fn address_file(config: &RuntimeConfig) -> PathBuf {
+    config.data.cache.join(handshake::ADDRESS_FILE)
 }
 
-const PORT_RANGE: (u16, u16) = (3000, 8000); // Define a sensible port range
+/// Receives status events while Shiny starts. The app forwards them to the
+/// frontend; the headless self-test prints them.
+pub type StatusSink = Arc<dyn Fn(&str, serde_json::Value) + Send + Sync>;
+
+/// A sink that emits each status as a Tauri event.
+pub fn app_sink(app_handle: &AppHandle) -> StatusSink {
+    let app_handle = app_handle.clone();
+    Arc::new(move |event, payload| {
+        app_handle
+            .emit(event, payload)
+            .unwrap_or_else(|e| log::warn!("Failed to emit {} event: {}", event, e));
+    })
+}
+
+pub(crate) fn emit(sink: &StatusSink, event: &str, payload: impl Serialize) {
+    sink(event, serde_json::to_value(payload).unwrap_or_default());
+}
+
+/// Checks the R version and required packages against the app's manifest,
+/// so an unusable setup is reported before anything is spawned.
+pub fn preflight(config: &RuntimeConfig, sink: &StatusSink) -> Result<RVersion, ShinyStartError> {
+    // Refuse to launch on an unsupported R rather than timing out later
+    emit(sink, "shiny-status", "Checking R version...");
+    let manifest = AppManifest::load(&config.shiny_app)?;
+    let found = r_version::detect(&config.rscript)
+        .map_err(|reason| ShinyStartError::RVersionUnknown { reason })?;
+    log::info!("Detected R {}", found);
+    manifest.r.check(found)?;
+
+    // Check packages natively so a missing one is reported up front
+    let report = r_packages::check(&config.library_paths(), &manifest.packages);
+    emit(sink, "shiny-packages", &report);
+    if !report.is_ok() {
+        return Err(ShinyStartError::MissingPackages { report });
+    }
+    Ok(found)
+}
 
-fn find_available_port(start: u16, end: u16) -> Option<u16> {
-    let shiny_url = env::var("SHINY_URL").unwrap_or_else(|_| "http://127.0.0.1".to_string());
-    let host = if shiny_url.starts_with("http://") {
-        &shiny_url["http://".len()..]
-    } else {
-        &shiny_url
+/// Starts the R Shiny app using the discovered R installation. The start
+/// can be aborted with `cancel_shiny_start`.
+#[tauri::command]
+pub async fn start_r_shiny(
+    runtime: tauri::State<'_, RuntimeState>,
+    settings: tauri::State<'_, SettingsState>,
+    supervisor: tauri::State<'_, ShinySupervisor>,
+) -> Result<String, ShinyStartError> {
+    let status = supervisor.status();
+    if let (ShinyState::Ready, Some(url)) = (status.state, status.url) {
+        return Ok(url);
+    }
+    start(
+        runtime.config().cloned(),
+        settings.current(),
+        &supervisor,
+        None,
+    )
+    .await
+}
+
+/// Stops the running app, if any, and starts it again behind the same proxy
+/// and secret, so the open browser tab reconnects.
+#[tauri::command]
+pub async fn restart_r_shiny(
+    runtime: tauri::State<'_, RuntimeState>,
+    settings: tauri::State<'_, SettingsState>,
+    supervisor: tauri::State<'_, ShinySupervisor>,
+) -> Result<String, ShinyStartError> {
+    if supervisor.is_starting() {
+        return Err(ShinyStartError::Launch(
+            "Shiny is already starting".to_string(),
+        ));
+    }
+
+    let status = supervisor.status();
+    let settings = settings.current();
+    // Nothing may be running, e.g. after a crash; restart starts it anyway
+    let _ = supervisor
+        .stop_for_restart(settings.launcher.shutdown_grace())
+        .await;
+    emit(supervisor.sink(), "shiny-stopped", ());
+    start(
+        runtime.config().cloned(),
+        settings,
+        &supervisor,
+        status.port,
+    )
+    .await
+}
+
+/// Aborts a start in progress and shuts down the half-started R process.
+#[tauri::command]
+pub async fn cancel_shiny_start(
+    settings: tauri::State<'_, SettingsState>,
+    supervisor: tauri::State<'_, ShinySupervisor>,
+) -> Result<(), String> {
+    if !supervisor.cancel_start() {
+        return Err("Shiny is not starting".to_string());
+    }
+    log::info!("Shiny start cancelled");
+    let _ = supervisor
+        .stop(settings.current().launcher.shutdown_grace())
+        .await;
+    emit(supervisor.sink(), "shiny-status", "Startup cancelled");
+    Ok(())
+}
+
+/// Runs preflight and launch as a task of its own, so that cancelling
+/// aborts it at the next await point.
+pub async fn start(
+    config: Result<RuntimeConfig, String>,
+    settings: Settings,
+    supervisor: &ShinySupervisor,
+    preferred_port: Option<u16>,
+) -> Result<String, ShinyStartError> {
+    let sink = supervisor.sink().clone();
+    let Some(token) = supervisor.try_begin_start() else {
+        return Err(ShinyStartError::Launch(
+            "Shiny is already starting".to_string(),
+        ));
     };
 
-    for port in start..end {
-        let addr = format!("{}:{}", host, port);
-        // If we can bind to the address, it's free.
-        if TcpListener::bind(&addr).is_ok() {
-            return Some(port);
+    let result = match config {
+        Ok(config) => {
+            let task_supervisor = supervisor.clone();
+            let task = tokio::spawn(async move {
+                let preflight_config = config.clone();
+                let preflight_sink = task_supervisor.sink().clone();
+                // Runs Rscript and reads package metadata, so keep it off
+                // the async workers
+                tokio::task::spawn_blocking(move || preflight(&preflight_config, &preflight_sink))
+                    .await
+                    .map_err(|e| ShinyStartError::Launch(e.to_string()))??;
+                launch(&config, &settings, &task_supervisor, preferred_port).await
+            });
+            supervisor.set_start_task(&token, task.abort_handle());
+            match task.await {
+                Ok(result) => result,
+                Err(e) if e.is_cancelled() => Err(ShinyStartError::Cancelled),
+                Err(e) => Err(ShinyStartError::Launch(e.to_string())),
+            }
         }
+        Err(e) => Err(ShinyStartError::from(e)),
+    };
+    supervisor.end_start(token);
+
+    match &result {
+        Err(ShinyStartError::Cancelled) => {}
+        Err(e) => emit(&sink, "shiny-error", e.to_string()),
+        Ok(_) => {}
     }
-    None
+    result
 }
 
-/// Starts the R Shiny app using the installed `r-win`.
-#[tauri::command]
-pub fn start_r_shiny(app_handle: tauri::AppHandle) -> Result<String, String> {
-    let rscript_path = env::var("RSCRIPT_PATH").expect("RSCRIPT_PATH not set");
-    let r_home = env::var("R_HOME_DIR").expect("R_HOME_DIR not set");
-    let start_shiny_path = env::var("START_SHINY_PATH").expect("START_SHINY_PATH not set");
-    let r_lib_path = env::var("R_LIB_PATH").expect("R_LIB_PATH not set");
-    let shiny_app_path = env::var("SHINY_APP_PATH").expect("SHINY_APP_PATH not set");
-    let shiny_url = env::var("SHINY_URL").expect("SHINY_URL not set");
+/// Binds the proxy's port: `preferred_port` if it is free, otherwise one
+/// from the configured range.
+fn reserve_public_port(
+    launcher: &LauncherSettings,
+    preferred_port: Option<u16>,
+) -> Result<TcpListener, ShinyStartError> {
+    preferred_port
+        .and_then(|port| TcpListener::bind((launcher.shiny_host.as_str(), port)).ok())
+        .or_else(|| {
+            reserve_port(
+                &launcher.shiny_host,
+                launcher.port_range_start,
+                launcher.port_range_end,
+            )
+            .map(|(_, listener)| listener)
+        })
+        .ok_or_else(|| {
+            ShinyStartError::Launch(format!(
+                "No available ports in range {}-{}",
+                launcher.port_range_start, launcher.port_range_end
+            ))
+        })
+}
+
+/// Spawns Rscript on a private port behind an authenticating proxy and waits
+/// until Shiny is serving, retrying with backoff. A proxy kept from a
+/// restart is reused; a new one takes `preferred_port` if it is free,
+/// otherwise one from the configured range.
+/// The process is handed to `supervisor`, which restarts it if it later
+/// crashes. Returns the proxy's URL, which carries this launch's secret.
+pub async fn launch(
+    config: &RuntimeConfig,
+    settings: &Settings,
+    supervisor: &ShinySupervisor,
+    preferred_port: Option<u16>,
+) -> Result<String, ShinyStartError> {
+    let sink = supervisor.sink();
+    let launcher = &settings.launcher;
+    let manifest = AppManifest::load(&config.shiny_app)?;
+    let probe = ReadyProbe::new(&manifest.readiness, launcher, &config.data.cache)?;
+    let mut r_env = r_env::build(config, &settings.r_env)?;
+    if let Some(path) = probe.ready_file() {
+        r_env.insert(READY_FILE_VAR.into(), path.into());
+    }
+    r_env.insert(ADDRESS_FILE_VAR.into(), address_file(config).into());
+    r_env.insert(
+        load_progress::PACKAGES_VAR.into(),
+        load_progress::declared_packages(&config.shiny_app)
+            .join(",")
+            .into(),
+    );
+    // Loopback only; other machines reach the app through LAN sharing
+    r_env.insert("RE_SHINY_HOST".into(), launcher.shiny_host.clone().into());
+    let generation = supervisor.begin_session();
+
+    // The proxy is the only address handed out, so only someone with the
+    // token gets through to R
+    let url = match supervisor.url().filter(|_| supervisor.has_proxy()) {
+        Some(url) => url,
+        None => {
+            let proxy = Proxy::serve(
+                reserve_public_port(launcher, preferred_port)?,
+                supervisor.upstream(launcher.host_ip()),
+            )
+            .map_err(|e| ShinyStartError::Launch(format!("Failed to start the proxy: {}", e)))?;
+            let url = proxy.url(&launcher.shiny_host);
+            supervisor.set_proxy(proxy, &url);
+            url
+        }
+    };
+    if let Some(secret) = supervisor.upstream_secret() {
+        r_env.insert(proxy::SECRET_VAR.into(), secret.into());
+    }
 
     let mut retries = 0;
-    let max_retries = 4;
+    let max_retries = launcher.max_retries;
     let mut delay = 1000; // Start with 1s delay, increase with retries
 
     while retries < max_retries {
         // Inform frontend we're attempting to start
-        let mut value = app_handle
-            .emit(
-                "shiny-status",
-                format!("Attempting to start (try {}/{})", retries + 1, max_retries),
-            )
-            .unwrap_or_else(|e| eprintln!("Failed to emit status event: {}", e));
+        emit(
+            sink,
+            "shiny-status",
+            format!("Attempting to start (try {}/{})", retries + 1, max_retries),
+        );
 
-        if let Some(port) = find_available_port(PORT_RANGE.0, PORT_RANGE.1) {
-            println!(
+        if let Some((port, reservation)) = reserve_private_port(&launcher.shiny_host) {
+            log::info!(
                 "Trying to launch Shiny app on port {} (Attempt {}/{})",
                 port,
                 retries + 1,
                 max_retries
             );
 
-            // Create command but don't spawn it yet
-            let mut command = Command::new(&rscript_path);
-
-            // Configure the command with all your arguments
-            command
-                .arg("--vanilla")
-                .arg(&start_shiny_path)
-                .arg("--verbose")
-                .env("RHOME", &r_home)
-                .env("R_HOME_DIR", &r_home)
-                .env("RE_SHINY_PORT", port.to_string())
-                .env("RE_SHINY_PATH", &shiny_app_path)
-                .env("RE_SHINY_HOST", "0.0.0.0") // Make Shiny bind to all interfaces
-                .env("R_LIBS", &r_lib_path)
-                .env("R_LIBS_USER", &r_lib_path)
-                .env("R_LIBS_SITE", &r_lib_path)
-                .env("R_LIB_PATHS", &r_lib_path)
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped());
-
-            // On Windows, add the CREATE_NO_WINDOW flag to hide the console window
-            #[cfg(target_os = "windows")]
-            {
-                const CREATE_NO_WINDOW: u32 = 0x08000000;
-                command.creation_flags(CREATE_NO_WINDOW);
-            }
-
-            // Now spawn the process
-            let process_result = command.spawn();
-
-            let (tx_ready, rx_ready) = std::sync::mpsc::channel::<bool>();
-            let (tx_stdout, rx_stdout) = std::sync::mpsc::channel::<String>();
-            let (tx_stderr, rx_stderr) = std::sync::mpsc::channel::<String>();
-
-            // When launching the process...
-            match process_result {
-                Ok(mut process) => {
-                    let pid = process.id();
-
-                    // For stdout
-                    if let Some(stdout) = process.stdout.take() {
-                        let tx_stdout_clone = tx_stdout.clone();
-                        let tx_ready_clone = tx_ready.clone();
-                        let app_handle_clone = app_handle.clone(); // Clone before moving
-                        std::thread::spawn(move || {
-                            use std::io::{BufRead, BufReader};
-                            let reader = BufReader::new(stdout);
-                            for line in reader.lines() {
-                                if let Ok(line) = line {
-                                    println!("SHINY OUT: {}", line);
-                                    let _ = tx_stdout_clone.send(line.clone());
-
-                                    // Check for signs of Shiny ready
-                                    if line.contains("Listening on") {
-                                        let _ = tx_ready_clone.send(true);
-                                    }
-                                }
-                            }
-                        });
-                    }
-
-                    // For stderr (similar pattern with additional package detection)
-                    if let Some(stderr) = process.stderr.take() {
-                        let tx_stderr_clone = tx_stderr.clone();
-                        let tx_ready_clone = tx_ready.clone();
-                        let app_handle_clone = app_handle.clone(); // Clone before moving
-                        std::thread::spawn(move || {
-                            use std::io::{BufRead, BufReader};
-                            let reader = BufReader::new(stderr);
-                            let mut package_loading_count = 0;
-                            let mut attaching_count = 0;
-
-                            for line in reader.lines() {
-                                if let Ok(line) = line {
-                                    println!("SHINY ERR: {}", line);
-                                    let _ = tx_stderr_clone.send(line.clone());
-
-                                    // Track package loading status
-                                    if line.contains("Loading required package:")
-                                        || line.contains("Attaching package:")
-                                    {
-                                        package_loading_count += 1;
-                                        attaching_count += 1;
-                                        app_handle_clone
-                                            .emit(
-                                                "shiny-status",
-                                                format!(
-                                                    "Loading packages ({} loaded)",
-                                                    package_loading_count
-                                                ),
-                                            )
-                                            .unwrap_or_default();
-                                    }
-
-                                    // Check for signs of Shiny ready
-                                    if line.contains("Listening on") {
-                                        let _ = tx_ready_clone.send(true);
-                                    }
-                                }
-                            }
-                        });
-                    }
-
-                    *R_PROCESS.lock().unwrap() = Some(process);
-
-                    // Set up the URL
-                    let full_url = format!("http://127.0.0.1:{}", port);
-                    println!("Waiting for Shiny server on {}", full_url);
-
-                    // Monitor for ready signal or timeout
-                    let client = Client::new();
-                    let timeout = Duration::from_secs(40); // Generous timeout for package loading
-                    let start_time = std::time::Instant::now();
-
-                    // Main monitoring loop
-                    app_handle
-                        .emit("shiny-status", "Waiting for packages to load...")
-                        .unwrap_or_default();
-
-                    // Wait for either "Listening on" message or timeout
-                    let mut is_ready = false;
-                    while start_time.elapsed() < timeout {
-                        // Check for ready message
-                        match rx_ready.try_recv() {
-                            Ok(true) => {
-                                is_ready = true;
-                                break;
+            match spawn_on_port(config, &r_env, port, Some(reservation), &probe, supervisor) {
+                Ok(lines) => {
+                    let port =
+                        match wait_until_ready(config, &probe, launcher, port, lines, supervisor)
+                            .await
+                        {
+                            Ok(port) => port,
+                            // Someone took the port before R bound it
+                            Err(ShinyStartError::PortInUse) if retries + 1 < max_retries => {
+                                log::warn!("Port {} was taken. Retrying...", port);
+                                retries += 1;
+                                continue;
                             }
-                            _ => {
-                                // If not ready yet, wait a bit then try connecting
-                                thread::sleep(Duration::from_millis(500));
-
-                                // Also check for TCP connectivity
-                                if let Ok(_) = TcpStream::connect(format!("127.0.0.1:{}", port)) {
-                                    // Try an HTTP request
-                                    match client
-                                        .head(&full_url)
-                                        .timeout(Duration::from_secs(1))
-                                        .send()
-                                    {
-                                        Ok(response) if response.status().is_success() => {
-                                            is_ready = true;
-                                            break;
-                                        }
-                                        _ => {}
-                                    }
-                                }
+                            Err(e) => {
+                                supervisor.clear_proxy();
+                                return Err(e);
                             }
-                        }
-                    }
-
-                    if is_ready {
-                        app_handle
-                            .emit("shiny-started", &full_url)
-                            .unwrap_or_else(|e| eprintln!("Failed to emit started event: {}", e));
-                        return Ok(full_url);
-                    } else {
-                        return Err(
-                            "Timed out waiting for Shiny to complete package loading".to_string()
-                        );
-                    }
+                        };
+                    emit(sink, "shiny-started", &url);
+                    watch(
+                        config.clone(),
+                        settings.clone(),
+                        r_env,
+                        probe,
+                        port,
+                        supervisor.clone(),
+                        generation,
+                    );
+                    return Ok(url);
                 }
                 Err(e) => {
-                    eprintln!("Failed to start Shiny app: {}. Retrying...", e);
+                    log::error!("Failed to start Shiny app: {}. Retrying...", e);
+                    supervisor.set_state(
+                        ShinyState::Crashed,
+                        Some(format!("Failed to start Rscript: {}", e)),
+                    );
                     retries += 1;
-                    thread::sleep(time::Duration::from_millis(delay));
+                    sleep(Duration::from_millis(delay)).await;
                     delay *= 2; // Exponential backoff
                 }
             }
         } else {
-            eprintln!(
-                "No available ports in range {}-{}. Retrying...",
-                PORT_RANGE.0, PORT_RANGE.1
-            );
+            log::warn!("No private port available for R. Retrying...");
             retries += 1;
-            thread::sleep(time::Duration::from_millis(delay));
+            sleep(Duration::from_millis(delay)).await;
             delay *= 2;
         }
     }
 
-    // Emit failure event
-    app_handle
-        .emit("shiny-error", "Failed to launch Shiny app")
-        .unwrap_or_else(|e| eprintln!("Failed to emit error event: {}", e));
+    supervisor.clear_proxy();
+    Err(diagnosis::diagnose(
+        &supervisor.stderr_tail(),
+        ShinyStartError::Launch("Failed to launch Shiny app.".to_string()),
+    ))
+}
 
-    Err("Failed to launch Shiny app.".to_string())
+/// Spawns Rscript, asking it to serve on `port`, and hands the child to
+/// `supervisor`. `reservation` holds the port until the last moment. Returns
+/// a channel receiving every line R writes to stdout and stderr.
+///
+/// The entry script, `assets/start-shiny.R` unless an app brings its own,
+/// is told what to do through the environment:
+///
+/// - `RE_SHINY_HOST` and `RE_SHINY_PORT`: where to serve.
+/// - `RE_SHINY_PATH`: the app directory.
+/// - `RE_SHINY_PACKAGES`: the app's declared packages, comma-separated, to
+///   load one at a time.
+/// - `RE_SHINY_SECRET`: answer only requests, WebSocket upgrades included,
+///   whose `X-RE-Shiny-Secret` header carries it, and refuse the rest with
+///   403, since anyone on the machine can reach the loopback port.
+///
+/// and reports back:
+///
+/// - any line of output as soon as it starts, ending the spawn phase;
+/// - `RE_SHINY_PACKAGE_LOADED <package>` on stderr after loading each
+///   package, for the progress bar;
+/// - `{"host": ..., "port": ...}` in the file named by
+///   `RE_SHINY_ADDRESS_FILE` once it is listening, written to a temporary
+///   file and renamed; without it the launcher falls back to Shiny's
+///   `Listening on` line;
+/// - for the handshake probe, the file named by `RE_SHINY_READY_FILE`.
+fn spawn_on_port(
+    config: &RuntimeConfig,
+    r_env: &BTreeMap<OsString, OsString>,
+    port: u16,
+    reservation: Option<TcpListener>,
+    probe: &ReadyProbe,
+    supervisor: &ShinySupervisor,
+) -> std::io::Result<UnboundedReceiver<String>> {
+    supervisor.set_state(ShinyState::Spawning, None);
+    probe.reset();
+    let _ = std::fs::remove_file(address_file(config));
+
+    // Create command but don't spawn it yet
+    let mut command = Command::new(&config.rscript);
+
+    // Configure the command with all your arguments
+    r_env::apply(command.as_std_mut(), r_env);
+    command
+        .arg("--vanilla")
+        .arg(&config.start_shiny)
+        .arg("--verbose")
+        .env("RE_SHINY_PORT", port.to_string())
+        .current_dir(&config.data.shiny_data)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // Backstop in case the child is dropped before the supervisor has it
+        .kill_on_drop(true);
+
+    process::isolate(command.as_std_mut());
+
+    // Free the port for R. Another process could still take it before R
+    // binds, which is why R reports the address it really got
+    drop(reservation);
+
+    // Now spawn the process
+    let mut process = command.spawn()?;
+    let (tx_lines, rx_lines) = unbounded_channel::<String>();
+
+    // For stdout
+    if let Some(stdout) = process.stdout.take() {
+        let tx_lines_clone = tx_lines.clone();
+        let supervisor_out = supervisor.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                supervisor_out.record_line(LogStream::Stdout, &line);
+                let _ = tx_lines_clone.send(line);
+            }
+        });
+    }
+
+    // For stderr (similar pattern with additional package detection)
+    if let Some(stderr) = process.stderr.take() {
+        let tx_lines_clone = tx_lines.clone();
+        let supervisor_clone = supervisor.clone(); // Clone before moving
+        let mut tracker = LoadTracker::new(&config.shiny_app, &config.data.cache);
+        tauri::async_runtime::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                supervisor_clone.record_line(LogStream::Stderr, &line);
+
+                // Track package loading status
+                if let Some(progress) = tracker.on_line(&line) {
+                    let sink = supervisor_clone.sink();
+                    emit(
+                        sink,
+                        "shiny-status",
+                        format!(
+                            "Loading packages ({}/{} loaded)",
+                            progress.loaded, progress.total
+                        ),
+                    );
+                    emit(sink, "shiny-package-progress", &progress);
+                }
+                let _ = tx_lines_clone.send(line);
+            }
+        });
+    }
+
+    supervisor.attach(process, port, &config.start_shiny);
+    Ok(rx_lines)
 }
 
-#[tauri::command]
-pub fn stop_r_shiny(app_handle: tauri::AppHandle) -> Result<(), String> {
-    let mut process_guard = R_PROCESS.lock().unwrap();
-    if let Some(mut child) = process_guard.take() {
-        // On Windows, try using taskkill to ensure all child processes are terminated
-        #[cfg(target_os = "windows")]
-        {
-            let pid = child.id();
-            std::process::Command::new("taskkill")
-                .args(&["/F", "/T", "/PID", &pid.to_string()])
-                .status()
-                .map_err(|e| format!("Failed to run taskkill: {}", e))?;
+/// Follows the app through the startup phases until `probe` passes, giving
+/// each phase its own timeout. The port comes from the address R reports,
+/// through the address file or Shiny's `Listening on` line, rather than from
+/// `requested_port`. Marks the supervisor ready and returns the port.
+async fn wait_until_ready(
+    config: &RuntimeConfig,
+    probe: &ReadyProbe,
+    launcher: &LauncherSettings,
+    requested_port: u16,
+    mut lines: UnboundedReceiver<String>,
+    supervisor: &ShinySupervisor,
+) -> Result<u16, ShinyStartError> {
+    log::info!("Waiting for Shiny server to report its address");
+    let address_file = address_file(config);
+    let client = reqwest::Client::new();
+    let mut address: Option<BoundAddress> = None;
+    let mut phase = StartupPhase::Spawn;
+    let mut phase_started = Instant::now();
+    let started = phase_started;
+    emit(supervisor.sink(), "shiny-status", "Starting R...");
+
+    loop {
+        if let Some(status) = supervisor.poll_exit() {
+            // Let the readers catch up, so R's last words are in the tail
+            let _ = tokio::time::timeout(Duration::from_secs(1), async {
+                while lines.recv().await.is_some() {}
+            })
+            .await;
+            return Err(diagnosis::diagnose(
+                &supervisor.stderr_tail(),
+                ShinyStartError::Launch(format!("R exited before Shiny was ready ({})", status)),
+            ));
         }
 
-        // Then try the standard kill method, mapping ExitStatus to ()
-        // Then try the standard kill method
-        match child.kill() {
-            Ok(_) => {
-                // Wait for process to exit
-                let _ = child.wait();
-                println!("R process successfully terminated");
+        if address.is_none() {
+            address = handshake::read_address_file(&address_file);
+        }
+        let mut ready = false;
+        let mut heard = false;
+        while let Ok(line) = lines.try_recv() {
+            heard = true;
+            ready |= probe.matches_line(&line);
+            if address.is_none() {
+                address = handshake::parse_listening_line(&line);
             }
-            Err(e) => {
-                println!("Kill failed (process may already be terminated): {}", e);
+        }
+
+        // R's first line shows it is up and running the entry script, which
+        // announces itself before it loads any packages
+        if phase == StartupPhase::Spawn && heard {
+            phase = StartupPhase::Packages;
+            phase_started = Instant::now();
+            emit(
+                supervisor.sink(),
+                "shiny-status",
+                "Waiting for packages to load...",
+            );
+        }
+        if phase != StartupPhase::Listen {
+            if let Some(address) = &address {
+                log::info!(
+                    "Shiny reported it is listening on {}:{}",
+                    address.host,
+                    address.port
+                );
+                phase = StartupPhase::Listen;
+                phase_started = Instant::now();
+                emit(
+                    supervisor.sink(),
+                    "shiny-status",
+                    "Waiting for the app to respond...",
+                );
             }
         }
-        println!("Exiting application...");
-        app_handle.exit(0);
-        Ok(())
-    } else {
-        Err("No R process running".to_string())
+
+        let url = address.as_ref().map(|a| a.url(&launcher.shiny_host));
+        let secret = supervisor.upstream_secret();
+        if ready || probe.poll(&client, url.as_deref(), secret.as_deref()).await {
+            let address = address.unwrap_or_else(|| {
+                log::warn!(
+                    "R did not report its address, assuming port {}",
+                    requested_port
+                );
+                BoundAddress {
+                    host: launcher.shiny_host.clone(),
+                    port: requested_port,
+                }
+            });
+            log::info!("Shiny is serving on {}", address.url(&launcher.shiny_host));
+            // The next start's ETA is based on this one
+            load_progress::record_startup(&config.data.cache, started.elapsed());
+            emit(
+                supervisor.sink(),
+                "shiny-package-progress",
+                LoadTracker::finished(&config.shiny_app),
+            );
+            supervisor.mark_ready(address.port);
+            return Ok(address.port);
+        }
+
+        let timeout = probe.timeout(phase);
+        if phase_started.elapsed() >= timeout {
+            log::error!("Timed out while {}", phase);
+            // Don't leave a half-started process behind
+            supervisor.discard(launcher.shutdown_grace()).await;
+            // R may be stuck on an error it printed rather than slow
+            return Err(diagnosis::diagnose(
+                &supervisor.stderr_tail(),
+                ShinyStartError::StartupTimeout {
+                    phase,
+                    timeout_secs: timeout.as_secs(),
+                },
+            ));
+        }
+        sleep(Duration::from_millis(500)).await;
     }
 }
+
+/// Watches a running process from a background task and, after an
+/// unexpected exit, restarts it on the same port with backoff until it is
+/// serving again or the crash limit is reached. Stops watching once the
+/// launch `generation` is superseded or the process is stopped.
+fn watch(
+    config: RuntimeConfig,
+    settings: Settings,
+    r_env: BTreeMap<OsString, OsString>,
+    probe: ReadyProbe,
+    mut port: u16,
+    supervisor: ShinySupervisor,
+    generation: u64,
+) {
+    tauri::async_runtime::spawn(async move {
+        let launcher = &settings.launcher;
+        let window = Duration::from_secs(launcher.crash_window_secs);
+        let sink = supervisor.sink();
+
+        loop {
+            sleep(WATCH_INTERVAL).await;
+            if !supervisor.is_current(generation) {
+                return;
+            }
+            let Some(status) = supervisor.poll_exit() else {
+                if supervisor.has_process() {
+                    continue;
+                }
+                return;
+            };
+            log::error!("R exited unexpectedly ({})", status);
+            let mut report = supervisor.record_crash(
+                status.to_string(),
+                status.code(),
+                launcher.crash_restart_limit,
+                window,
+            );
+
+            // Keep trying until R is serving again or we give up
+            while report.restarting {
+                // Back off 1s, 2s, 4s, ... by the number of recent crashes
+                let delay = 1000u64 << (report.recent_crashes - 1).min(5);
+                emit(
+                    sink,
+                    "shiny-status",
+                    format!("R crashed, restarting in {}s", delay / 1000),
+                );
+                sleep(Duration::from_millis(delay)).await;
+                if !supervisor.is_current(generation) {
+                    return;
+                }
+
+                let reservation = TcpListener::bind((launcher.shiny_host.as_str(), port)).ok();
+                let result =
+                    match spawn_on_port(&config, &r_env, port, reservation, &probe, &supervisor) {
+                        Ok(lines) => {
+                            wait_until_ready(&config, &probe, launcher, port, lines, &supervisor)
+                                .await
+                        }
+                        Err(e) => Err(ShinyStartError::Launch(format!(
+                            "Failed to start Rscript: {}",
+                            e
+                        ))),
+                    };
+                match result {
+                    Ok(bound_port) => {
+                        port = bound_port;
+                        log::info!("Shiny restarted on port {}", port);
+                        // Same proxy and secret, so open pages reconnect
+                        emit(sink, "shiny-started", supervisor.url());
+                        break;
+                    }
+                    Err(e) => {
+                        log::error!("Failed to restart Shiny: {}", e);
+                        report = supervisor.record_crash(
+                            e.to_string(),
+                            None,
+                            launcher.crash_restart_limit,
+                            window,
+                        );
+                    }
+                }
+            }
+            if !report.restarting {
+                return;
+            }
+        }
+    });
+}
+
+/// Stops the running app without quitting; see `quit_app` for that. Also
+/// aborts a start in progress.
+#[tauri::command]
+pub async fn stop_r_shiny(
+    settings: tauri::State<'_, SettingsState>,
+    supervisor: tauri::State<'_, ShinySupervisor>,
+) -> Result<(), String> {
+    supervisor.cancel_start();
+    supervisor
+        .stop(settings.current().launcher.shutdown_grace())
+        .await?;
+    emit(supervisor.sink(), "shiny-stopped", ());
+    Ok(())
+}
+
+/// Quits the application. R is stopped by the exit handler.
+#[tauri::command]
+pub fn quit_app(app_handle: AppHandle) {
+    log::info!("Exiting application...");
+    app_handle.exit(0);
+}
+
+/// Stops R, if running, as the app exits, along with every app from the
+/// registry.
+pub fn stop_on_exit(app_handle: &AppHandle) {
+    let Some(supervisor) = app_handle.try_state::<ShinySupervisor>() else {
+        return;
+    };
+    if supervisor.has_process() {
+        log::info!("Application exiting, stopping R...");
+    }
+    lan_share::stop_on_exit(app_handle);
+    let mut supervisors = vec![supervisor.inner().clone()];
+    if let Some(registry) = app_handle.try_state::<AppRegistry>() {
+        supervisors.extend(registry.supervisors().cloned());
+    }
+    let grace = app_handle
+        .state::<SettingsState>()
+        .current()
+        .launcher
+        .shutdown_grace();
+    // Stopped together so the grace periods don't add up. Also cancels a
+    // restart that may be pending after a crash
+    let stops: Vec<_> = supervisors
+        .into_iter()
+        .map(|supervisor| {
+            supervisor.cancel_start();
+            tauri::async_runtime::spawn(async move { supervisor.stop(grace).await })
+        })
+        .collect();
+    tauri::async_runtime::block_on(async {
+        for stop in stops {
+            let _ = stop.await;
+        }
+    });
+}
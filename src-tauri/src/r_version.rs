@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::process::Command;
+
+use crate::process;
+
+/// An R version such as `4.3.1`. Missing components compare as zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct RVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl RVersion {
+    /// Parses `4.3.1`, or the version in R's own banner such as
+    /// `R version 4.3.1 (2023-06-16)` or `Rscript (R) version 4.3.1`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let version = text
+            .split_whitespace()
+            .find(|word| word.starts_with(|c: char| c.is_ascii_digit()))
+            .unwrap_or(text.trim());
+        let mut parts = version.split(['.', '-']).map(|p| p.parse::<u32>());
+        let mut next = |required: bool| match parts.next() {
+            Some(Ok(n)) => Ok(n),
+            None if !required => Ok(0),
+            _ => Err(format!("'{}' is not a valid version", text.trim())),
+        };
+        Ok(Self {
+            major: next(true)?,
+            minor: next(false)?,
+            patch: next(false)?,
+        })
+    }
+}
+
+impl fmt::Display for RVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl TryFrom<String> for RVersion {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::parse(&value)
+    }
+}
+
+impl From<RVersion> for String {
+    fn from(value: RVersion) -> Self {
+        value.to_string()
+    }
+}
+
+/// Runs `rscript` to ask R for its version.
+pub fn detect(rscript: &Path) -> Result<RVersion, String> {
+    let mut command = Command::new(rscript);
+    command.args(["--vanilla", "-e", "cat(format(getRversion()))"]);
+    process::hide_window(&mut command);
+    let output = command
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", rscript.display(), e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "{} exited with {}: {}",
+            rscript.display(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    RVersion::parse(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(major: u32, minor: u32, patch: u32) -> RVersion {
+        RVersion {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    #[test]
+    fn parses_plain_versions() {
+        assert_eq!(RVersion::parse("4.3.1\n"), Ok(version(4, 3, 1)));
+        assert_eq!(RVersion::parse("4.1"), Ok(version(4, 1, 0)));
+        assert_eq!(RVersion::parse("4"), Ok(version(4, 0, 0)));
+    }
+
+    #[test]
+    fn parses_r_banners() {
+        let banner = "R version 4.3.2 (2023-10-31) -- \"Eye Holes\"\nCopyright (C) 2023";
+        assert_eq!(RVersion::parse(banner), Ok(version(4, 3, 2)));
+        let rscript = "Rscript (R) version 4.3.2 (2023-10-31)";
+        assert_eq!(RVersion::parse(rscript), Ok(version(4, 3, 2)));
+    }
+
+    #[test]
+    fn rejects_text_without_a_version() {
+        assert!(RVersion::parse("").is_err());
+        assert!(RVersion::parse("R version unknown").is_err());
+        assert!(RVersion::parse("4.x").is_err());
+    }
+
+    #[test]
+    fn orders_by_component() {
+        assert!(version(4, 10, 0) > version(4, 9, 1));
+        assert!(version(4, 3, 1) < version(5, 0, 0));
+    }
+}
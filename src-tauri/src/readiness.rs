@@ -0,0 +1,255 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::proxy::SECRET_HEADER;
+use crate::settings::LauncherSettings;
+
+/// Environment variable telling R where to write the handshake file.
+pub const READY_FILE_VAR: &str = "RE_SHINY_READY_FILE";
+
+/// How the launcher tells that an app is ready, from the `[readiness]`
+/// table of its manifest:
+///
+/// ```toml
+/// [readiness.probe]
+/// strategy = "http"
+/// path = "/health"
+///
+/// [readiness.timeouts]
+/// packages_secs = 120
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Readiness {
+    pub probe: ProbeStrategy,
+    pub timeouts: PhaseTimeouts,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum ProbeStrategy {
+    /// A line R writes to stdout or stderr matches `pattern`.
+    Log {
+        #[serde(default = "default_pattern")]
+        pattern: String,
+    },
+    /// `GET path` answers with `status` and, if set, a body containing
+    /// `body_marker`.
+    Http {
+        #[serde(default = "default_path")]
+        path: String,
+        #[serde(default = "default_status")]
+        status: u16,
+        #[serde(default)]
+        body_marker: Option<String>,
+    },
+    /// R creates `file` in the cache directory once it is serving. Its full
+    /// path is passed in `RE_SHINY_READY_FILE`.
+    Handshake {
+        #[serde(default = "default_ready_file")]
+        file: String,
+    },
+}
+
+fn default_pattern() -> String {
+    "Listening on".to_string()
+}
+
+fn default_path() -> String {
+    "/".to_string()
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+fn default_ready_file() -> String {
+    "shiny-ready".to_string()
+}
+
+impl Default for ProbeStrategy {
+    fn default() -> Self {
+        Self::Log {
+            pattern: default_pattern(),
+        }
+    }
+}
+
+/// Limits for each startup phase, in seconds. Unset spawn and package
+/// limits fall back to `launcher.ready_timeout_secs`, since an entry script
+/// that says nothing until it has loaded its packages spends that time in
+/// the spawn phase; the listen limit falls back to 15 seconds.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PhaseTimeouts {
+    pub spawn_secs: Option<u64>,
+    pub packages_secs: Option<u64>,
+    pub listen_secs: Option<u64>,
+}
+
+/// Startup phases, each with its own timeout: R starting up until it writes
+/// its first line, loading packages until R reports its address, and the
+/// app coming up until the probe passes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupPhase {
+    Spawn,
+    Packages,
+    Listen,
+}
+
+impl StartupPhase {
+    /// The setting that limits this phase.
+    pub fn timeout_setting(self) -> &'static str {
+        match self {
+            Self::Spawn => {
+                "readiness.timeouts.spawn_secs in the app's app.toml, or launcher.ready_timeout_secs if that is unset"
+            }
+            Self::Packages => {
+                "readiness.timeouts.packages_secs in the app's app.toml, or launcher.ready_timeout_secs if that is unset"
+            }
+            Self::Listen => "readiness.timeouts.listen_secs in the app's app.toml",
+        }
+    }
+}
+
+impl fmt::Display for StartupPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Spawn => "starting R",
+            Self::Packages => "loading packages",
+            Self::Listen => "waiting for the app to respond",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Check {
+    Log(Regex),
+    Http {
+        path: String,
+        status: u16,
+        body_marker: Option<String>,
+    },
+    Handshake(PathBuf),
+}
+
+/// A readiness probe ready to run against a launched app.
+#[derive(Debug, Clone)]
+pub struct ReadyProbe {
+    check: Check,
+    spawn_timeout: Duration,
+    packages_timeout: Duration,
+    listen_timeout: Duration,
+}
+
+impl ReadyProbe {
+    pub fn new(
+        readiness: &Readiness,
+        launcher: &LauncherSettings,
+        cache_dir: &Path,
+    ) -> Result<Self, String> {
+        let check = match &readiness.probe {
+            ProbeStrategy::Log { pattern } => Check::Log(
+                Regex::new(pattern)
+                    .map_err(|e| format!("Invalid readiness pattern '{}': {}", pattern, e))?,
+            ),
+            ProbeStrategy::Http {
+                path,
+                status,
+                body_marker,
+            } => Check::Http {
+                path: format!("/{}", path.trim_start_matches('/')),
+                status: *status,
+                body_marker: body_marker.clone(),
+            },
+            ProbeStrategy::Handshake { file } => Check::Handshake(cache_dir.join(file)),
+        };
+        let t = &readiness.timeouts;
+        Ok(Self {
+            check,
+            spawn_timeout: Duration::from_secs(t.spawn_secs.unwrap_or(launcher.ready_timeout_secs)),
+            packages_timeout: Duration::from_secs(
+                t.packages_secs.unwrap_or(launcher.ready_timeout_secs),
+            ),
+            listen_timeout: Duration::from_secs(t.listen_secs.unwrap_or(15)),
+        })
+    }
+
+    pub fn timeout(&self, phase: StartupPhase) -> Duration {
+        match phase {
+            StartupPhase::Spawn => self.spawn_timeout,
+            StartupPhase::Packages => self.packages_timeout,
+            StartupPhase::Listen => self.listen_timeout,
+        }
+    }
+
+    /// The handshake file R should write, if the probe uses one.
+    pub fn ready_file(&self) -> Option<&Path> {
+        match &self.check {
+            Check::Handshake(path) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Clears state left by a previous launch. Call before spawning.
+    pub fn reset(&self) {
+        if let Some(path) = self.ready_file() {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    /// Whether a line of R's output signals readiness.
+    pub fn matches_line(&self, line: &str) -> bool {
+        match &self.check {
+            Check::Log(pattern) => pattern.is_match(line),
+            _ => false,
+        }
+    }
+
+    /// Checks the app at `base_url`, once known, for readiness, sending
+    /// `secret` as the proxy would. Always false for the log strategy, which
+    /// only looks at output.
+    pub async fn poll(
+        &self,
+        client: &reqwest::Client,
+        base_url: Option<&str>,
+        secret: Option<&str>,
+    ) -> bool {
+        match &self.check {
+            Check::Log(_) => false,
+            Check::Handshake(path) => path.exists(),
+            Check::Http {
+                path,
+                status,
+                body_marker,
+            } => {
+                let Some(base_url) = base_url else {
+                    return false;
+                };
+                let mut request = client
+                    .get(format!("{}{}", base_url, path))
+                    .timeout(Duration::from_secs(2));
+                if let Some(secret) = secret {
+                    request = request.header(SECRET_HEADER, secret);
+                }
+                let Ok(response) = request.send().await else {
+                    return false;
+                };
+                if response.status().as_u16() != *status {
+                    return false;
+                }
+                match body_marker {
+                    Some(marker) => response
+                        .text()
+                        .await
+                        .is_ok_and(|body| body.contains(marker.as_str())),
+                    None => true,
+                }
+            }
+        }
+    }
+}
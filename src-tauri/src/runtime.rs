@@ -0,0 +1,412 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tauri::{AppHandle, Manager};
+
+use crate::manifest::AppManifest;
+use crate::r_discovery::{self, RDiscovery, RInstallation, RSource};
+use crate::r_version;
+use crate::settings::PathsLayer;
+
+/// Resolves the read-only `assets` directory: `./assets` in development,
+/// beside the executable in portable mode, and the bundled resource
+/// directory otherwise. Nothing is ever written here.
+pub fn assets_dir(app_handle: &AppHandle, portable_root: Option<&Path>) -> PathBuf {
+    assets_dir_in(app_handle.path().resource_dir().ok(), portable_root)
+}
+
+/// Like [`assets_dir`], given the resource directory instead of the app.
+pub fn assets_dir_in(resource_dir: Option<PathBuf>, portable_root: Option<&Path>) -> PathBuf {
+    if cfg!(debug_assertions) {
+        std::env::current_dir()
+            .unwrap_or_else(|_| PathBuf::new())
+            .join("assets")
+    } else if let Some(root) = portable_root {
+        root.join("assets")
+    } else {
+        resource_dir
+            .map(|dir| dir.join("assets"))
+            .unwrap_or_else(legacy_assets_dir)
+    }
+}
+
+/// Where release builds used to keep everything, next to the executable.
+pub fn legacy_assets_dir() -> PathBuf {
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe_path| exe_path.parent().map(|p| p.to_path_buf()))
+        .unwrap_or_else(|| ".".into())
+        .join("assets")
+}
+
+/// Writable locations for everything the app or R produces: per-user by
+/// default, or beside the executable in portable mode.
+#[derive(Debug, Clone, Serialize)]
+pub struct DataDirs {
+    pub portable: bool,
+    pub data: PathBuf,
+    pub config: PathBuf,
+    /// Packages the user installs; searched before the bundled library.
+    pub user_lib: PathBuf,
+    /// Working directory for the Shiny app's own files.
+    pub shiny_data: PathBuf,
+    pub cache: PathBuf,
+    pub logs: PathBuf,
+    /// Where downloaded updates are staged in portable mode.
+    pub updates: PathBuf,
+}
+
+impl DataDirs {
+    pub fn new(data: &Path, config: &Path, cache: &Path, logs: &Path) -> Self {
+        Self {
+            portable: false,
+            data: data.to_path_buf(),
+            config: config.to_path_buf(),
+            user_lib: data.join("r-library"),
+            shiny_data: data.join("shiny-data"),
+            cache: cache.to_path_buf(),
+            logs: logs.to_path_buf(),
+            updates: data.join("updates"),
+        }
+    }
+
+    /// Keeps everything under `<root>/data`.
+    pub fn portable(root: &Path) -> Self {
+        let data = root.join("data");
+        Self {
+            portable: true,
+            ..Self::new(
+                &data,
+                &data.join("config"),
+                &data.join("cache"),
+                &data.join("logs"),
+            )
+        }
+    }
+
+    /// Uses the portable layout when `portable_root` is set, otherwise the
+    /// platform's app data, config, cache and log directories.
+    pub fn resolve(app_handle: &AppHandle, portable_root: Option<&Path>) -> Result<Self, String> {
+        if let Some(root) = portable_root {
+            return Ok(Self::portable(root));
+        }
+        let path = app_handle.path();
+        let data = path.app_data_dir().map_err(|e| e.to_string())?;
+        let config = path.app_config_dir().map_err(|e| e.to_string())?;
+        let cache = path.app_cache_dir().map_err(|e| e.to_string())?;
+        let logs = path.app_log_dir().map_err(|e| e.to_string())?;
+        Ok(Self::new(&data, &config, &cache, &logs))
+    }
+
+    /// Like [`DataDirs::resolve`], without an app: lays the directories out
+    /// the way Tauri does on desktop, under the app's `identifier`.
+    pub fn resolve_headless(
+        identifier: &str,
+        portable_root: Option<&Path>,
+    ) -> Result<Self, String> {
+        if let Some(root) = portable_root {
+            return Ok(Self::portable(root));
+        }
+        let unknown = |kind: &str| format!("Unknown {} directory", kind);
+        let data = dirs::data_dir().ok_or_else(|| unknown("data"))?;
+        let config = dirs::config_dir().ok_or_else(|| unknown("config"))?;
+        let cache = dirs::cache_dir().ok_or_else(|| unknown("cache"))?;
+        #[cfg(target_os = "macos")]
+        let logs = dirs::home_dir()
+            .ok_or_else(|| unknown("home"))?
+            .join("Library/Logs")
+            .join(identifier);
+        #[cfg(not(target_os = "macos"))]
+        let logs = dirs::data_local_dir()
+            .ok_or_else(|| unknown("local data"))?
+            .join(identifier)
+            .join("logs");
+        Ok(Self::new(
+            &data.join(identifier),
+            &config.join(identifier),
+            &cache.join(identifier),
+            &logs,
+        ))
+    }
+
+    pub fn create_all(&self) -> Result<(), String> {
+        for dir in [
+            &self.data,
+            &self.config,
+            &self.user_lib,
+            &self.shiny_data,
+            &self.cache,
+            &self.logs,
+            &self.updates,
+        ] {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Resolved paths for the bundled R runtime and Shiny app.
+///
+/// Built once in `setup` and held in Tauri managed state so commands never
+/// have to read these values back out of the process environment.
+#[derive(Debug, Clone, Serialize)]
+pub struct RuntimeConfig {
+    pub r_home: PathBuf,
+    pub r_lib: PathBuf,
+    /// Libraries searched after the user's: the configured or bundled
+    /// library, or those a system R reports, site libraries first.
+    pub site_libs: Vec<PathBuf>,
+    pub rscript: PathBuf,
+    pub shiny_app: PathBuf,
+    pub start_shiny: PathBuf,
+    pub data: DataDirs,
+}
+
+impl RuntimeConfig {
+    /// Lays out the runtime paths for an R installation, a read-only
+    /// `assets` directory holding the Shiny app and the writable data
+    /// directories, then applies any configured paths.
+    pub fn new(
+        assets_dir: &Path,
+        r: &RInstallation,
+        data: &DataDirs,
+        overrides: &PathsLayer,
+    ) -> Self {
+        let r_lib = overrides
+            .r_lib
+            .clone()
+            .unwrap_or_else(|| r.r_home.join("library"));
+        let rscript = overrides
+            .rscript
+            .clone()
+            .unwrap_or_else(|| r.rscript.clone());
+        // A system R's packages may live in site libraries outside R_HOME
+        let site_libs = if overrides.r_lib.is_none() && r.source != RSource::Bundled {
+            r_discovery::query_libraries(&rscript)
+                .map_err(|e| log::warn!("Failed to ask R for its libraries: {}", e))
+                .ok()
+                .filter(|libs| !libs.is_empty())
+                .unwrap_or_else(|| vec![r_lib.clone()])
+        } else {
+            vec![r_lib.clone()]
+        };
+        Self {
+            data: data.clone(),
+            r_lib,
+            site_libs,
+            rscript,
+            r_home: r.r_home.clone(),
+            shiny_app: overrides
+                .shiny_app
+                .clone()
+                .unwrap_or_else(|| assets_dir.join("shiny")),
+            start_shiny: overrides
+                .start_shiny
+                .clone()
+                .unwrap_or_else(|| assets_dir.join("start-shiny.R")),
+        }
+    }
+
+    /// Checks that every file the launcher needs is present.
+    pub fn validate(&self) -> Result<(), String> {
+        let required = [
+            ("Rscript", &self.rscript),
+            ("start-shiny.R", &self.start_shiny),
+            ("Shiny app", &self.shiny_app),
+        ];
+        let missing: Vec<String> = required
+            .iter()
+            .filter(|(_, path)| !path.exists())
+            .map(|(name, path)| format!("{} not found at {}", name, path.display()))
+            .collect();
+
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(missing.join("; "))
+        }
+    }
+
+    /// Library directories in search order: the user's library, the site
+    /// libraries, then R's own library for the base packages.
+    pub fn library_paths(&self) -> Vec<PathBuf> {
+        let mut libraries = vec![self.data.user_lib.clone()];
+        libraries.extend(self.site_libs.iter().cloned());
+        let home_lib = self.r_home.join("library");
+        if !libraries.contains(&home_lib) {
+            libraries.push(home_lib);
+        }
+        libraries
+    }
+}
+
+/// Managed state holding the resolved runtime paths, the reason they are
+/// unusable if validation failed, and how R was found.
+///
+/// Finding R and asking it for its libraries runs Rscript, which can take
+/// seconds, so nothing is resolved until first asked for; call the accessors
+/// off the main thread.
+pub struct RuntimeState {
+    assets_dir: PathBuf,
+    data: DataDirs,
+    overrides: PathsLayer,
+    resolved: OnceLock<Resolved>,
+}
+
+struct Resolved {
+    config: Option<RuntimeConfig>,
+    error: Option<String>,
+    discovery: RDiscovery,
+}
+
+impl RuntimeState {
+    pub fn new(assets_dir: &Path, data: &DataDirs, overrides: &PathsLayer) -> Self {
+        Self {
+            assets_dir: assets_dir.to_path_buf(),
+            data: data.clone(),
+            overrides: overrides.clone(),
+            resolved: OnceLock::new(),
+        }
+    }
+
+    fn resolved(&self) -> &Resolved {
+        self.resolved.get_or_init(|| {
+            let discovery =
+                r_discovery::discover(&self.assets_dir, self.overrides.r_home.as_deref());
+            let Some(r) = &discovery.selected else {
+                let tried: Vec<String> = discovery
+                    .candidates
+                    .iter()
+                    .map(|c| c.path.display().to_string())
+                    .collect();
+                log::error!("No R installation found (tried {})", tried.join(", "));
+                return Resolved {
+                    config: None,
+                    error: Some(format!(
+                        "No R installation found. Looked in: {}",
+                        tried.join(", ")
+                    )),
+                    discovery,
+                };
+            };
+
+            let config = RuntimeConfig::new(&self.assets_dir, r, &self.data, &self.overrides);
+            let error = config.validate().err().map(|e| {
+                log::error!("Runtime configuration is invalid: {}", e);
+                format!("Runtime configuration is invalid: {}", e)
+            });
+            Resolved {
+                config: Some(config),
+                error,
+                discovery,
+            }
+        })
+    }
+
+    /// Returns the config, or the startup error as a command error.
+    pub fn config(&self) -> Result<&RuntimeConfig, String> {
+        let resolved = self.resolved();
+        match (&resolved.error, &resolved.config) {
+            (None, Some(config)) => Ok(config),
+            (Some(e), _) => Err(e.clone()),
+            (None, None) => Err("Runtime configuration is not available".to_string()),
+        }
+    }
+
+    /// Returns how R was found.
+    pub fn discovery(&self) -> &RDiscovery {
+        &self.resolved().discovery
+    }
+}
+
+/// A path the launcher depends on and whether it is present.
+#[derive(Debug, Clone, Serialize)]
+pub struct PathInfo {
+    pub path: PathBuf,
+    pub exists: bool,
+}
+
+impl PathInfo {
+    fn of(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            exists: path.exists(),
+        }
+    }
+}
+
+/// Everything support staff need to see about the runtime, as returned by
+/// `get_runtime_info`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RuntimeInfo {
+    pub app_version: String,
+    pub error: Option<String>,
+    pub r_source: Option<RSource>,
+    pub r_home: Option<PathInfo>,
+    pub rscript: Option<PathInfo>,
+    pub r_version: Option<String>,
+    pub r_version_error: Option<String>,
+    pub library_paths: Vec<PathInfo>,
+    pub shiny_app: Option<PathInfo>,
+    pub shiny_app_version: Option<String>,
+    pub start_shiny: Option<PathInfo>,
+    pub data: Option<DataDirs>,
+}
+
+/// Reports the resolved runtime paths, versions and which files exist.
+#[tauri::command]
+pub async fn get_runtime_info(
+    app_handle: AppHandle,
+    runtime: tauri::State<'_, RuntimeState>,
+) -> Result<RuntimeInfo, String> {
+    let resolved = runtime.resolved();
+    let config = resolved.config.as_ref();
+    // Starting R can take seconds; keep it off the main thread
+    let detected = match config.map(|c| c.rscript.clone()) {
+        Some(rscript) => Some(
+            tokio::task::spawn_blocking(move || r_version::detect(&rscript))
+                .await
+                .map_err(|e| e.to_string())?,
+        ),
+        None => None,
+    };
+    let (r_version, r_version_error) = match detected {
+        Some(Ok(version)) => (Some(version.to_string()), None),
+        Some(Err(e)) => (None, Some(e)),
+        None => (None, None),
+    };
+
+    Ok(RuntimeInfo {
+        app_version: app_handle.package_info().version.to_string(),
+        error: resolved.error.clone(),
+        r_source: resolved.discovery.selected.as_ref().map(|r| r.source),
+        r_home: config.map(|c| PathInfo::of(&c.r_home)),
+        rscript: config.map(|c| PathInfo::of(&c.rscript)),
+        r_version,
+        r_version_error,
+        library_paths: config
+            .map(|c| c.library_paths())
+            .unwrap_or_default()
+            .iter()
+            .map(|p| PathInfo::of(p))
+            .collect(),
+        shiny_app: config.map(|c| PathInfo::of(&c.shiny_app)),
+        shiny_app_version: config
+            .and_then(|c| AppManifest::load(&c.shiny_app).ok())
+            .and_then(|m| m.version),
+        start_shiny: config.map(|c| PathInfo::of(&c.start_shiny)),
+        data: config.map(|c| c.data.clone()),
+    })
+}
+
+/// Returns the resolved Rscript path.
+#[tauri::command]
+pub async fn get_rscript_path(runtime: tauri::State<'_, RuntimeState>) -> Result<String, String> {
+    let resolved = runtime.resolved();
+    resolved
+        .config
+        .as_ref()
+        .map(|c| c.rscript.display().to_string())
+        .ok_or_else(|| runtime.config().unwrap_err())
+}
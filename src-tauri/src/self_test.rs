@@ -0,0 +1,218 @@
+use serde::Serialize;
+use std::fmt::Display;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::r_shiny::{self, StatusSink};
+use crate::runtime::{self, DataDirs, RuntimeState};
+use crate::settings::SettingsState;
+use crate::supervisor::ShinySupervisor;
+
+/// Command-line flag that runs the self-test instead of the app.
+pub const FLAG: &str = "--self-test";
+
+pub fn requested() -> bool {
+    std::env::args().skip(1).any(|arg| arg == FLAG)
+}
+
+#[derive(Serialize)]
+struct StepReport {
+    name: &'static str,
+    ok: bool,
+    duration_ms: u128,
+    detail: serde_json::Value,
+}
+
+#[derive(Serialize)]
+struct SelfTestReport {
+    ok: bool,
+    app_version: String,
+    steps: Vec<StepReport>,
+}
+
+#[derive(Default)]
+struct Steps(Vec<StepReport>);
+
+impl Steps {
+    /// Runs one step, recording its outcome. Returns the value on success.
+    fn run<T: Serialize, E: Serialize + Display>(
+        &mut self,
+        name: &'static str,
+        f: impl FnOnce() -> Result<T, E>,
+    ) -> Option<T> {
+        eprintln!("self-test: {}...", name);
+        let started = Instant::now();
+        let result = f();
+        let (ok, detail) = match &result {
+            Ok(value) => (true, serde_json::to_value(value).unwrap_or_default()),
+            Err(e) => {
+                eprintln!("self-test: {} failed: {}", name, e);
+                (false, serde_json::to_value(e).unwrap_or_default())
+            }
+        };
+        self.0.push(StepReport {
+            name,
+            ok,
+            duration_ms: started.elapsed().as_millis(),
+            detail,
+        });
+        result.ok()
+    }
+}
+
+/// Lets a release build, which has no console of its own on Windows, print
+/// to the terminal it was started from.
+#[cfg(target_os = "windows")]
+fn attach_console() {
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn attach_console() {}
+
+/// Requests the app through its proxy the way a browser would: refused
+/// without the secret, then let in by the cookie the secret is traded for.
+fn proxied_request(url: &str) -> Result<serde_json::Value, String> {
+    let client = reqwest::blocking::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .timeout(Duration::from_secs(10))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let base = url.split('?').next().unwrap_or(url);
+    let get = |target: &str, cookie: Option<&str>| {
+        let mut request = client.get(target);
+        if let Some(cookie) = cookie {
+            request = request.header(reqwest::header::COOKIE, cookie);
+        }
+        request
+            .send()
+            .map_err(|e| format!("Request to {} failed: {}", base, e))
+    };
+
+    let status = get(base, None)?.status();
+    if status != reqwest::StatusCode::FORBIDDEN {
+        return Err(format!("{} without the secret returned {}", base, status));
+    }
+
+    let response = get(url, None)?;
+    let cookie = response
+        .headers()
+        .get(reqwest::header::SET_COOKIE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(str::to_string)
+        .ok_or_else(|| format!("{} did not set a cookie ({})", base, response.status()))?;
+
+    let status = get(base, Some(&cookie))?.status();
+    if status.is_success() {
+        Ok(serde_json::json!({ "url": base, "status": status.as_u16() }))
+    } else {
+        Err(format!("{} returned {}", base, status))
+    }
+}
+
+/// Writes log records to stderr, since stdout is reserved for the report.
+struct StderrLogger;
+
+impl log::Log for StderrLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Info
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!(
+                "[{}][{}] {}",
+                record.level(),
+                record.target(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Resolves the runtime, checks R and its packages, starts Shiny, requests
+/// the app over HTTP and shuts it down again. No Tauri app is built, so it
+/// also runs where there is no display, e.g. over SSH. Prints a JSON report
+/// to stdout and returns the process exit code.
+pub fn run(context: tauri::Context, portable_root: Option<&Path>) -> i32 {
+    attach_console();
+    static LOGGER: StderrLogger = StderrLogger;
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(log::LevelFilter::Info);
+    }
+    let package_info = context.package_info();
+    let identifier = context.config().identifier.clone();
+    // R's output is already echoed line by line
+    let sink: StatusSink = Arc::new(|event, payload| {
+        if event != "shiny-log" {
+            eprintln!("[{}] {}", event, payload);
+        }
+    });
+    let supervisor = ShinySupervisor::new(sink.clone());
+    let mut steps = Steps::default();
+
+    let runtime = steps.run("resolve_runtime", || {
+        let data_dirs = DataDirs::resolve_headless(&identifier, portable_root)?;
+        data_dirs.create_all()?;
+        let settings = SettingsState::load(&data_dirs.config).current();
+        settings.validate()?;
+        let resource_dir =
+            tauri::utils::platform::resource_dir(package_info, &tauri::Env::default()).ok();
+        let assets_dir = runtime::assets_dir_in(resource_dir, portable_root);
+        let state = RuntimeState::new(&assets_dir, &data_dirs, &settings.paths);
+        state.config().map(|config| (config.clone(), settings))
+    });
+
+    if let Some((config, settings)) = runtime {
+        let ready = steps
+            .run("preflight", || r_shiny::preflight(&config, &sink))
+            .is_some();
+
+        let url = if ready {
+            steps.run("start_shiny", || {
+                tauri::async_runtime::block_on(r_shiny::launch(
+                    &config,
+                    &settings,
+                    &supervisor,
+                    None,
+                ))
+            })
+        } else {
+            None
+        };
+
+        if let Some(url) = url {
+            steps.run("http_request", || proxied_request(&url));
+            steps.run("shutdown", || {
+                tauri::async_runtime::block_on(supervisor.stop(settings.launcher.shutdown_grace()))
+            });
+        }
+    }
+
+    // A step that never ran because an earlier one failed is a failure too
+    let finished = steps.0.iter().any(|step| step.name == "shutdown");
+    let report = SelfTestReport {
+        ok: finished && steps.0.iter().all(|step| step.ok),
+        app_version: package_info.version.to_string(),
+        steps: steps.0,
+    };
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&report).unwrap_or_default()
+    );
+    if report.ok {
+        0
+    } else {
+        1
+    }
+}
@@ -0,0 +1,704 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+const SETTINGS_FILE: &str = "settings.toml";
+
+/// One settings file. Every value is optional so a layer only overrides
+/// what it mentions.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SettingsLayer {
+    pub launcher: LauncherLayer,
+    pub paths: PathsLayer,
+    pub r_env: REnvLayer,
+    pub logging: LoggingLayer,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LauncherLayer {
+    pub port_range_start: Option<u16>,
+    pub port_range_end: Option<u16>,
+    pub ready_timeout_secs: Option<u64>,
+    pub max_retries: Option<u32>,
+    pub shiny_host: Option<String>,
+    pub crash_restart_limit: Option<u32>,
+    pub crash_window_secs: Option<u64>,
+    pub shutdown_grace_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PathsLayer {
+    pub r_home: Option<PathBuf>,
+    pub r_lib: Option<PathBuf>,
+    pub rscript: Option<PathBuf>,
+    pub shiny_app: Option<PathBuf>,
+    pub start_shiny: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct REnvLayer {
+    pub inherit: Option<Vec<String>>,
+    pub locale: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingLayer {
+    pub max_file_size_kb: Option<u64>,
+    pub max_files: Option<usize>,
+    pub retention_days: Option<u64>,
+}
+
+/// Effective launcher settings after all layers are applied.
+#[derive(Debug, Clone, Serialize)]
+pub struct LauncherSettings {
+    pub port_range_start: u16,
+    pub port_range_end: u16,
+    pub ready_timeout_secs: u64,
+    pub max_retries: u32,
+    pub shiny_host: String,
+    /// Crashes tolerated within `crash_window_secs` before giving up;
+    /// 0 turns automatic restarts off.
+    pub crash_restart_limit: u32,
+    pub crash_window_secs: u64,
+    /// How long R may take to exit after being asked before it is killed.
+    /// Windows has no way to ask, so R is killed right away there.
+    pub shutdown_grace_secs: u64,
+}
+
+impl LauncherSettings {
+    pub fn shutdown_grace(&self) -> Duration {
+        Duration::from_secs(self.shutdown_grace_secs)
+    }
+
+    /// `shiny_host` as an address; validation keeps it on loopback.
+    pub fn host_ip(&self) -> IpAddr {
+        self.shiny_host
+            .parse()
+            .unwrap_or(IpAddr::from([127, 0, 0, 1]))
+    }
+}
+
+impl Default for LauncherSettings {
+    fn default() -> Self {
+        Self {
+            port_range_start: 3000,
+            port_range_end: 8000,
+            ready_timeout_secs: 40, // Generous timeout for package loading
+            max_retries: 4,
+            shiny_host: "127.0.0.1".to_string(),
+            crash_restart_limit: 3,
+            crash_window_secs: 300,
+            shutdown_grace_secs: 5,
+        }
+    }
+}
+
+/// How the R process environment is built. Only the platform essentials
+/// and the variables named in `inherit` are passed through from the parent.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct REnvSettings {
+    pub inherit: Vec<String>,
+    /// Pins `LANG` and `LC_ALL`; `None` keeps the system locale.
+    pub locale: Option<String>,
+}
+
+/// Log files kept in the app log directory. The current file is rotated
+/// once it reaches `max_file_size_kb`; rotated files are deleted at startup
+/// and on each rotation when older than `retention_days` or beyond the
+/// newest `max_files`.
+#[derive(Debug, Clone, Serialize)]
+pub struct LoggingSettings {
+    pub max_file_size_kb: u64,
+    pub max_files: usize,
+    pub retention_days: u64,
+}
+
+impl Default for LoggingSettings {
+    fn default() -> Self {
+        Self {
+            max_file_size_kb: 1024,
+            max_files: 10,
+            retention_days: 30,
+        }
+    }
+}
+
+/// Effective settings. Paths left as `None` are discovered automatically.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Settings {
+    pub launcher: LauncherSettings,
+    pub paths: PathsLayer,
+    pub r_env: REnvSettings,
+    pub logging: LoggingSettings,
+}
+
+/// Which layer an effective value came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SettingSource {
+    Default,
+    Machine,
+    User,
+}
+
+/// Effective settings plus where each value came from, as returned by
+/// `get_settings`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SettingsReport {
+    pub settings: Settings,
+    pub sources: BTreeMap<String, SettingSource>,
+    pub user_file: PathBuf,
+    pub machine_file: PathBuf,
+    pub errors: Vec<String>,
+}
+
+/// Merges `value` from the machine and user layers over `default`,
+/// recording which layer won under `key`.
+fn pick<T: Clone>(
+    sources: &mut BTreeMap<String, SettingSource>,
+    key: &str,
+    default: T,
+    machine: &Option<T>,
+    user: &Option<T>,
+) -> T {
+    let (value, source) = match (user, machine) {
+        (Some(v), _) => (v.clone(), SettingSource::User),
+        (None, Some(v)) => (v.clone(), SettingSource::Machine),
+        (None, None) => (default, SettingSource::Default),
+    };
+    sources.insert(key.to_string(), source);
+    value
+}
+
+/// Like [`pick`] for a value that is unset by default.
+fn pick_optional<T: Clone>(
+    sources: &mut BTreeMap<String, SettingSource>,
+    key: &str,
+    machine: &Option<T>,
+    user: &Option<T>,
+) -> Option<T> {
+    pick(
+        sources,
+        key,
+        None,
+        &machine.clone().map(Some),
+        &user.clone().map(Some),
+    )
+}
+
+/// Applies the machine and user layers over the built-in defaults.
+pub fn merge(
+    machine: &SettingsLayer,
+    user: &SettingsLayer,
+) -> (Settings, BTreeMap<String, SettingSource>) {
+    let mut sources = BTreeMap::new();
+    let defaults = LauncherSettings::default();
+    let (m, u) = (&machine.launcher, &user.launcher);
+    let launcher = LauncherSettings {
+        port_range_start: pick(
+            &mut sources,
+            "launcher.port_range_start",
+            defaults.port_range_start,
+            &m.port_range_start,
+            &u.port_range_start,
+        ),
+        port_range_end: pick(
+            &mut sources,
+            "launcher.port_range_end",
+            defaults.port_range_end,
+            &m.port_range_end,
+            &u.port_range_end,
+        ),
+        ready_timeout_secs: pick(
+            &mut sources,
+            "launcher.ready_timeout_secs",
+            defaults.ready_timeout_secs,
+            &m.ready_timeout_secs,
+            &u.ready_timeout_secs,
+        ),
+        max_retries: pick(
+            &mut sources,
+            "launcher.max_retries",
+            defaults.max_retries,
+            &m.max_retries,
+            &u.max_retries,
+        ),
+        shiny_host: pick(
+            &mut sources,
+            "launcher.shiny_host",
+            defaults.shiny_host,
+            &m.shiny_host,
+            &u.shiny_host,
+        ),
+        crash_restart_limit: pick(
+            &mut sources,
+            "launcher.crash_restart_limit",
+            defaults.crash_restart_limit,
+            &m.crash_restart_limit,
+            &u.crash_restart_limit,
+        ),
+        crash_window_secs: pick(
+            &mut sources,
+            "launcher.crash_window_secs",
+            defaults.crash_window_secs,
+            &m.crash_window_secs,
+            &u.crash_window_secs,
+        ),
+        shutdown_grace_secs: pick(
+            &mut sources,
+            "launcher.shutdown_grace_secs",
+            defaults.shutdown_grace_secs,
+            &m.shutdown_grace_secs,
+            &u.shutdown_grace_secs,
+        ),
+    };
+
+    let (m, u) = (&machine.paths, &user.paths);
+    let paths = PathsLayer {
+        r_home: pick_optional(&mut sources, "paths.r_home", &m.r_home, &u.r_home),
+        r_lib: pick_optional(&mut sources, "paths.r_lib", &m.r_lib, &u.r_lib),
+        rscript: pick_optional(&mut sources, "paths.rscript", &m.rscript, &u.rscript),
+        shiny_app: pick_optional(&mut sources, "paths.shiny_app", &m.shiny_app, &u.shiny_app),
+        start_shiny: pick_optional(
+            &mut sources,
+            "paths.start_shiny",
+            &m.start_shiny,
+            &u.start_shiny,
+        ),
+    };
+
+    let env_defaults = REnvSettings::default();
+    let (m, u) = (&machine.r_env, &user.r_env);
+    let r_env = REnvSettings {
+        inherit: pick(
+            &mut sources,
+            "r_env.inherit",
+            env_defaults.inherit,
+            &m.inherit,
+            &u.inherit,
+        ),
+        locale: pick_optional(&mut sources, "r_env.locale", &m.locale, &u.locale),
+    };
+
+    let log_defaults = LoggingSettings::default();
+    let (m, u) = (&machine.logging, &user.logging);
+    let logging = LoggingSettings {
+        max_file_size_kb: pick(
+            &mut sources,
+            "logging.max_file_size_kb",
+            log_defaults.max_file_size_kb,
+            &m.max_file_size_kb,
+            &u.max_file_size_kb,
+        ),
+        max_files: pick(
+            &mut sources,
+            "logging.max_files",
+            log_defaults.max_files,
+            &m.max_files,
+            &u.max_files,
+        ),
+        retention_days: pick(
+            &mut sources,
+            "logging.retention_days",
+            log_defaults.retention_days,
+            &m.retention_days,
+            &u.retention_days,
+        ),
+    };
+
+    (
+        Settings {
+            launcher,
+            paths,
+            r_env,
+            logging,
+        },
+        sources,
+    )
+}
+
+impl Settings {
+    /// Checks that the effective settings are usable.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut problems = Vec::new();
+        let l = &self.launcher;
+
+        if l.port_range_start < 1024 {
+            problems.push("launcher.port_range_start must be 1024 or higher".to_string());
+        }
+        if l.port_range_start >= l.port_range_end {
+            problems.push(format!(
+                "launcher.port_range_start ({}) must be below port_range_end ({})",
+                l.port_range_start, l.port_range_end
+            ));
+        }
+        if !(1..=3600).contains(&l.ready_timeout_secs) {
+            problems.push("launcher.ready_timeout_secs must be between 1 and 3600".to_string());
+        }
+        if !(1..=20).contains(&l.max_retries) {
+            problems.push("launcher.max_retries must be between 1 and 20".to_string());
+        }
+        if l.crash_restart_limit > 20 {
+            problems.push("launcher.crash_restart_limit must be 20 or lower".to_string());
+        }
+        if !(1..=86400).contains(&l.crash_window_secs) {
+            problems.push("launcher.crash_window_secs must be between 1 and 86400".to_string());
+        }
+        if l.shutdown_grace_secs > 60 {
+            problems.push("launcher.shutdown_grace_secs must be 60 or lower".to_string());
+        }
+        // The gradebook must not be reachable from the network by accident;
+        // LAN sharing exposes it deliberately, behind a token
+        let loopback = l.shiny_host == "localhost"
+            || l.shiny_host
+                .parse::<IpAddr>()
+                .is_ok_and(|ip| ip.is_loopback());
+        if !loopback {
+            problems.push(format!(
+                "launcher.shiny_host '{}' must be a loopback address",
+                l.shiny_host
+            ));
+        }
+
+        for name in &self.r_env.inherit {
+            if name.is_empty() || name.contains('=') {
+                problems.push(format!("r_env.inherit has an invalid name '{}'", name));
+            }
+        }
+        if self
+            .r_env
+            .locale
+            .as_ref()
+            .is_some_and(|locale| locale.trim().is_empty())
+        {
+            problems.push("r_env.locale must not be empty".to_string());
+        }
+
+        let g = &self.logging;
+        if !(64..=102400).contains(&g.max_file_size_kb) {
+            problems.push("logging.max_file_size_kb must be between 64 and 102400".to_string());
+        }
+        if !(1..=100).contains(&g.max_files) {
+            problems.push("logging.max_files must be between 1 and 100".to_string());
+        }
+        if !(1..=365).contains(&g.retention_days) {
+            problems.push("logging.retention_days must be between 1 and 365".to_string());
+        }
+
+        let p = &self.paths;
+        for (key, path) in [
+            ("paths.r_home", &p.r_home),
+            ("paths.r_lib", &p.r_lib),
+            ("paths.rscript", &p.rscript),
+            ("paths.shiny_app", &p.shiny_app),
+            ("paths.start_shiny", &p.start_shiny),
+        ] {
+            if let Some(path) = path {
+                if !path.exists() {
+                    problems.push(format!("{} does not exist: {}", key, path.display()));
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems.join("; "))
+        }
+    }
+}
+
+/// Machine-wide settings file, managed by IT rather than the user.
+pub fn machine_settings_path() -> PathBuf {
+    if cfg!(target_os = "windows") {
+        std::env::var_os("ProgramData")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(r"C:\ProgramData"))
+            .join("grade-tool-tauri")
+            .join(SETTINGS_FILE)
+    } else if cfg!(target_os = "macos") {
+        PathBuf::from("/Library/Application Support/grade-tool-tauri").join(SETTINGS_FILE)
+    } else {
+        PathBuf::from("/etc/grade-tool-tauri").join(SETTINGS_FILE)
+    }
+}
+
+/// Reads one layer. A missing file is an empty layer; a malformed one is
+/// reported and ignored.
+fn read_layer(path: &Path, errors: &mut Vec<String>) -> SettingsLayer {
+    match std::fs::read_to_string(path) {
+        Ok(text) => toml::from_str(&text).unwrap_or_else(|e| {
+            log::warn!("Ignoring invalid settings file {}: {}", path.display(), e);
+            errors.push(format!("{}: {}", path.display(), e));
+            SettingsLayer::default()
+        }),
+        Err(_) => SettingsLayer::default(),
+    }
+}
+
+/// Merges the layers, dropping only the invalid one so a typo in the user
+/// file does not also discard the machine-wide config.
+fn resolve(
+    machine: &SettingsLayer,
+    user: &SettingsLayer,
+    errors: &mut Vec<String>,
+) -> (Settings, BTreeMap<String, SettingSource>) {
+    let empty = SettingsLayer::default();
+    let (mut settings, mut sources) = merge(machine, user);
+    if let Err(e) = settings.validate() {
+        log::warn!("Settings are invalid, ignoring the user layer: {}", e);
+        errors.push(e);
+        (settings, sources) = merge(machine, &empty);
+        if let Err(e) = settings.validate() {
+            log::warn!("Machine settings are invalid, using defaults: {}", e);
+            errors.push(e);
+            (settings, sources) = merge(&empty, &empty);
+        }
+    }
+    (settings, sources)
+}
+
+/// Managed state holding the settings layers and their merged result.
+pub struct SettingsState {
+    inner: Mutex<SettingsReport>,
+    machine: SettingsLayer,
+}
+
+impl SettingsState {
+    /// Loads the machine layer and the user layer from `config_dir`.
+    pub fn load(config_dir: &Path) -> Self {
+        let mut errors = Vec::new();
+        let machine_file = machine_settings_path();
+        let user_file = config_dir.join(SETTINGS_FILE);
+
+        let machine = read_layer(&machine_file, &mut errors);
+        let user = read_layer(&user_file, &mut errors);
+        let (settings, sources) = resolve(&machine, &user, &mut errors);
+
+        Self {
+            inner: Mutex::new(SettingsReport {
+                settings,
+                sources,
+                user_file,
+                machine_file,
+                errors,
+            }),
+            machine,
+        }
+    }
+
+    pub fn current(&self) -> Settings {
+        self.inner.lock().unwrap().settings.clone()
+    }
+
+    pub fn report(&self) -> SettingsReport {
+        self.inner.lock().unwrap().clone()
+    }
+}
+
+/// Returns the effective settings and the layer each value came from.
+#[tauri::command]
+pub fn get_settings(state: tauri::State<'_, SettingsState>) -> SettingsReport {
+    state.report()
+}
+
+/// Validates and saves the user settings layer. Launcher values apply to the
+/// next Shiny start; path changes apply after the app is restarted.
+#[tauri::command]
+pub fn set_settings(
+    state: tauri::State<'_, SettingsState>,
+    user: SettingsLayer,
+) -> Result<SettingsReport, String> {
+    let (settings, sources) = merge(&state.machine, &user);
+    settings.validate()?;
+
+    let mut report = state.inner.lock().unwrap();
+    let user_file = report.user_file.clone();
+    if let Some(dir) = user_file.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    let text = toml::to_string_pretty(&user).map_err(|e| e.to_string())?;
+    std::fs::write(&user_file, text)
+        .map_err(|e| format!("Failed to write {}: {}", user_file.display(), e))?;
+
+    report.settings = settings;
+    report.sources = sources;
+    report.errors.clear();
+    Ok(report.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(toml: &str) -> SettingsLayer {
+        toml::from_str(toml).unwrap()
+    }
+
+    fn problems(edit: impl FnOnce(&mut Settings)) -> String {
+        let mut settings = Settings::default();
+        edit(&mut settings);
+        settings.validate().unwrap_err()
+    }
+
+    #[test]
+    fn pick_prefers_user_then_machine_then_default() {
+        let mut sources = BTreeMap::new();
+        assert_eq!(pick(&mut sources, "a", 1, &Some(2), &Some(3)), 3);
+        assert_eq!(pick(&mut sources, "b", 1, &Some(2), &None), 2);
+        assert_eq!(pick(&mut sources, "c", 1, &None, &None), 1);
+        assert_eq!(sources["a"], SettingSource::User);
+        assert_eq!(sources["b"], SettingSource::Machine);
+        assert_eq!(sources["c"], SettingSource::Default);
+    }
+
+    #[test]
+    fn merge_records_the_source_of_each_value() {
+        let machine = layer(
+            "[launcher]\nmax_retries = 2\nready_timeout_secs = 60\n[paths]\nr_home = '/opt/R'",
+        );
+        let user = layer("[launcher]\nmax_retries = 5");
+        let (settings, sources) = merge(&machine, &user);
+
+        assert_eq!(settings.launcher.max_retries, 5);
+        assert_eq!(settings.launcher.ready_timeout_secs, 60);
+        assert_eq!(settings.launcher.port_range_start, 3000);
+        assert_eq!(settings.paths.r_home, Some(PathBuf::from("/opt/R")));
+        assert_eq!(sources["launcher.max_retries"], SettingSource::User);
+        assert_eq!(
+            sources["launcher.ready_timeout_secs"],
+            SettingSource::Machine
+        );
+        assert_eq!(sources["launcher.port_range_start"], SettingSource::Default);
+        assert_eq!(sources["paths.r_home"], SettingSource::Machine);
+        assert_eq!(sources["paths.rscript"], SettingSource::Default);
+    }
+
+    #[test]
+    fn locale_is_inherited_unless_configured() {
+        let (settings, sources) = merge(&SettingsLayer::default(), &SettingsLayer::default());
+        assert_eq!(settings.r_env.locale, None);
+        assert_eq!(sources["r_env.locale"], SettingSource::Default);
+
+        let user = layer("[r_env]\nlocale = 'C.UTF-8'");
+        let (settings, sources) = merge(&SettingsLayer::default(), &user);
+        assert_eq!(settings.r_env.locale.as_deref(), Some("C.UTF-8"));
+        assert_eq!(sources["r_env.locale"], SettingSource::User);
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        assert!(Settings::default().validate().is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_port_ranges() {
+        assert!(problems(|s| s.launcher.port_range_start = 80).contains("1024 or higher"));
+        assert!(problems(|s| s.launcher.port_range_end = 3000).contains("must be below"));
+    }
+
+    #[test]
+    fn validate_rejects_out_of_range_launcher_values() {
+        for (edit, key) in [
+            (
+                (|s: &mut Settings| s.launcher.ready_timeout_secs = 0) as fn(&mut Settings),
+                "ready_timeout_secs",
+            ),
+            (
+                |s| s.launcher.ready_timeout_secs = 3601,
+                "ready_timeout_secs",
+            ),
+            (|s| s.launcher.max_retries = 0, "max_retries"),
+            (|s| s.launcher.max_retries = 21, "max_retries"),
+            (
+                |s| s.launcher.crash_restart_limit = 21,
+                "crash_restart_limit",
+            ),
+            (|s| s.launcher.crash_window_secs = 0, "crash_window_secs"),
+            (
+                |s| s.launcher.shutdown_grace_secs = 61,
+                "shutdown_grace_secs",
+            ),
+        ] {
+            assert!(problems(edit).contains(key), "{}", key);
+        }
+    }
+
+    #[test]
+    fn validate_keeps_shiny_host_on_loopback() {
+        for host in ["0.0.0.0", "192.168.1.10", "example.com"] {
+            let error = problems(|s| s.launcher.shiny_host = host.to_string());
+            assert!(error.contains("must be a loopback address"), "{}", host);
+        }
+        for host in ["127.0.0.1", "::1", "localhost"] {
+            let mut settings = Settings::default();
+            settings.launcher.shiny_host = host.to_string();
+            assert!(settings.validate().is_ok(), "{}", host);
+        }
+    }
+
+    #[test]
+    fn validate_rejects_bad_env_logging_and_paths() {
+        assert!(problems(|s| s.r_env.inherit = vec!["A=B".to_string()]).contains("r_env.inherit"));
+        assert!(problems(|s| s.r_env.locale = Some(" ".to_string())).contains("r_env.locale"));
+        assert!(problems(|s| s.logging.max_file_size_kb = 1).contains("max_file_size_kb"));
+        assert!(problems(|s| s.logging.max_files = 0).contains("max_files"));
+        assert!(problems(|s| s.logging.retention_days = 0).contains("retention_days"));
+        let missing = PathBuf::from("/definitely/not/here");
+        assert!(problems(|s| s.paths.rscript = Some(missing)).contains("paths.rscript"));
+    }
+
+    #[test]
+    fn validate_reports_every_problem() {
+        let error = problems(|s| {
+            s.launcher.max_retries = 0;
+            s.logging.max_files = 0;
+        });
+        assert!(error.contains("max_retries") && error.contains("max_files"));
+    }
+
+    #[test]
+    fn resolve_drops_only_the_invalid_user_layer() {
+        let machine = layer("[launcher]\nmax_retries = 2");
+        let user = layer("[launcher]\nshiny_host = '0.0.0.0'");
+        let mut errors = Vec::new();
+        let (settings, sources) = resolve(&machine, &user, &mut errors);
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(settings.launcher.shiny_host, "127.0.0.1");
+        assert_eq!(settings.launcher.max_retries, 2);
+        assert_eq!(sources["launcher.max_retries"], SettingSource::Machine);
+    }
+
+    #[test]
+    fn resolve_falls_back_to_defaults_when_the_machine_layer_is_invalid() {
+        let machine = layer("[launcher]\nmax_retries = 0");
+        let user = layer("[launcher]\nready_timeout_secs = 90");
+        let mut errors = Vec::new();
+        let (settings, sources) = resolve(&machine, &user, &mut errors);
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(settings.launcher.max_retries, 4);
+        assert_eq!(settings.launcher.ready_timeout_secs, 40);
+        assert_eq!(sources["launcher.max_retries"], SettingSource::Default);
+    }
+
+    #[test]
+    fn resolve_keeps_valid_layers() {
+        let user = layer("[launcher]\nready_timeout_secs = 90");
+        let mut errors = Vec::new();
+        let (settings, _) = resolve(&SettingsLayer::default(), &user, &mut errors);
+
+        assert!(errors.is_empty());
+        assert_eq!(settings.launcher.ready_timeout_secs, 90);
+    }
+}
@@ -0,0 +1,213 @@
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::fmt;
+
+use crate::r_packages::PackageReport;
+use crate::r_version::RVersion;
+use crate::readiness::StartupPhase;
+
+/// Why the Shiny app could not be started.
+///
+/// Serialized for the frontend as `{ kind, message, hint, ...details }` so
+/// the UI can branch on `kind` and still show `message` and `hint` as-is.
+#[derive(Debug, Clone)]
+pub enum ShinyStartError {
+    /// Rscript could not be run or its version could not be read.
+    RVersionUnknown { reason: String },
+    /// The selected R is outside the range the app's manifest supports.
+    IncompatibleRVersion {
+        found: RVersion,
+        min: Option<RVersion>,
+        max: Option<RVersion>,
+    },
+    /// Required R packages are missing from the library or too old.
+    MissingPackages { report: PackageReport },
+    /// A startup phase took longer than its timeout allows.
+    StartupTimeout {
+        phase: StartupPhase,
+        timeout_secs: u64,
+    },
+    /// R could not load a package; see `diagnosis`.
+    PackageNotFound { package: String },
+    /// R could not open a file the app needs.
+    FileNotFound { path: String },
+    /// Shiny could not bind its port.
+    PortInUse,
+    /// The app's R code has a syntax error.
+    ParseError { detail: String },
+    /// R could not set up the configured locale or encoding.
+    LocaleError { detail: String },
+    /// R ran out of memory.
+    OutOfMemory { size: String },
+    /// The user cancelled the start.
+    Cancelled,
+    /// Any other launch failure.
+    Launch(String),
+}
+
+impl ShinyStartError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::RVersionUnknown { .. } => "r_version_unknown",
+            Self::IncompatibleRVersion { .. } => "incompatible_r_version",
+            Self::MissingPackages { .. } => "missing_packages",
+            Self::StartupTimeout { .. } => "startup_timeout",
+            Self::PackageNotFound { .. } => "package_not_found",
+            Self::FileNotFound { .. } => "file_not_found",
+            Self::PortInUse => "port_in_use",
+            Self::ParseError { .. } => "parse_error",
+            Self::LocaleError { .. } => "locale_error",
+            Self::OutOfMemory { .. } => "out_of_memory",
+            Self::Cancelled => "cancelled",
+            Self::Launch(_) => "launch",
+        }
+    }
+
+    /// What the user can do about it, where there is something to do.
+    pub fn hint(&self) -> Option<String> {
+        let hint = match self {
+            Self::RVersionUnknown { .. } => {
+                "Check that R is installed, or set paths.r_home in the settings.".to_string()
+            }
+            Self::IncompatibleRVersion { .. } => {
+                "Install a supported version of R, or set paths.r_home to one.".to_string()
+            }
+            Self::MissingPackages { .. } => {
+                "Install the packages in R with install.packages(), or reinstall the app."
+                    .to_string()
+            }
+            Self::StartupTimeout { phase, .. } => format!(
+                "The computer may be busy; try again, or raise {}.",
+                phase.timeout_setting()
+            ),
+            Self::PackageNotFound { package } => format!(
+                "Install it in R with install.packages(\"{}\"), or reinstall the app.",
+                package
+            ),
+            Self::FileNotFound { .. } => {
+                "The app's files may be incomplete; reinstall the app or check paths.shiny_app."
+                    .to_string()
+            }
+            Self::PortInUse => {
+                "Another program holds the port; close it or change launcher.port_range_start."
+                    .to_string()
+            }
+            Self::ParseError { .. } => {
+                "The app's R code has a syntax error; reinstall the app or contact its maintainer."
+                    .to_string()
+            }
+            Self::LocaleError { .. } => {
+                "Set r_env.locale to a locale installed on this computer, such as en_US.UTF-8 or C.UTF-8, or remove it to use the system locale."
+                    .to_string()
+            }
+            Self::OutOfMemory { .. } => {
+                "Close other programs to free memory and try again.".to_string()
+            }
+            Self::Cancelled | Self::Launch(_) => return None,
+        };
+        Some(hint)
+    }
+}
+
+impl fmt::Display for ShinyStartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RVersionUnknown { reason } => {
+                write!(f, "Could not determine the R version: {}", reason)
+            }
+            Self::IncompatibleRVersion { found, min, max } => {
+                let range = match (min, max) {
+                    (Some(min), Some(max)) => format!("between {} and {}", min, max),
+                    (Some(min), None) => format!("{} or newer", min),
+                    (None, Some(max)) => format!("{} or older", max),
+                    (None, None) => "a different version".to_string(),
+                };
+                write!(
+                    f,
+                    "R {} is installed, but this app needs R {}. Please install a supported version of R.",
+                    found, range
+                )
+            }
+            Self::MissingPackages { report } => {
+                let mut problems: Vec<String> = report
+                    .missing
+                    .iter()
+                    .map(|p| format!("{} (not installed)", p.name))
+                    .collect();
+                problems.extend(report.outdated.iter().map(|p| {
+                    format!(
+                        "{} ({} installed, {} required)",
+                        p.name, p.installed, p.required
+                    )
+                }));
+                write!(
+                    f,
+                    "Some R packages the app needs are not available: {}",
+                    problems.join(", ")
+                )
+            }
+            Self::StartupTimeout {
+                phase,
+                timeout_secs,
+            } => write!(
+                f,
+                "The app did not start: timed out after {}s while {}",
+                timeout_secs, phase
+            ),
+            Self::PackageNotFound { package } => {
+                write!(f, "R could not load the package '{}'", package)
+            }
+            Self::FileNotFound { path } => write!(f, "R could not open the file {}", path),
+            Self::PortInUse => f.write_str("The app's port is already in use"),
+            Self::ParseError { detail } => {
+                write!(f, "The app's code could not be parsed: {}", detail)
+            }
+            Self::LocaleError { detail } => {
+                write!(f, "R could not set up its locale: {}", detail)
+            }
+            Self::OutOfMemory { size } => {
+                write!(f, "R ran out of memory allocating {}", size)
+            }
+            Self::Cancelled => f.write_str("Starting the app was cancelled"),
+            Self::Launch(message) => f.write_str(message),
+        }
+    }
+}
+
+impl From<String> for ShinyStartError {
+    fn from(message: String) -> Self {
+        Self::Launch(message)
+    }
+}
+
+impl Serialize for ShinyStartError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("kind", self.kind())?;
+        map.serialize_entry("message", &self.to_string())?;
+        map.serialize_entry("hint", &self.hint())?;
+        match self {
+            Self::RVersionUnknown { reason } => map.serialize_entry("reason", reason)?,
+            Self::IncompatibleRVersion { found, min, max } => {
+                map.serialize_entry("found", found)?;
+                map.serialize_entry("min", min)?;
+                map.serialize_entry("max", max)?;
+            }
+            Self::MissingPackages { report } => map.serialize_entry("report", report)?,
+            Self::StartupTimeout {
+                phase,
+                timeout_secs,
+            } => {
+                map.serialize_entry("phase", phase)?;
+                map.serialize_entry("timeout_secs", timeout_secs)?;
+            }
+            Self::PackageNotFound { package } => map.serialize_entry("package", package)?,
+            Self::FileNotFound { path } => map.serialize_entry("path", path)?,
+            Self::ParseError { detail } | Self::LocaleError { detail } => {
+                map.serialize_entry("detail", detail)?
+            }
+            Self::OutOfMemory { size } => map.serialize_entry("size", size)?,
+            Self::PortInUse | Self::Cancelled | Self::Launch(_) => {}
+        }
+        map.end()
+    }
+}
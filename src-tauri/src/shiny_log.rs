@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::app_registry::AppRegistry;
+use crate::r_shiny::StatusSink;
+use crate::supervisor::ShinySupervisor;
+
+/// Lines kept in memory; older ones are dropped.
+const CAPACITY: usize = 5000;
+
+/// Lines returned by `get_shiny_logs` when no limit is given.
+const DEFAULT_LIMIT: usize = 500;
+
+/// Which of R's output streams a line came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+impl LogStream {
+    /// Target the line is logged under.
+    fn target(self) -> &'static str {
+        match self {
+            Self::Stdout => "r::stdout",
+            Self::Stderr => "r::stderr",
+        }
+    }
+}
+
+/// Severity guessed from the line. R writes messages, warnings and errors
+/// alike to stderr, so the stream alone says little.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+impl LogLevel {
+    fn classify(stream: LogStream, line: &str) -> Self {
+        if stream == LogStream::Stdout {
+            return Self::Info;
+        }
+        let line = line.trim_start();
+        if line.starts_with("Error") || line.starts_with("Execution halted") {
+            Self::Error
+        } else if line.starts_with("Warning") {
+            Self::Warning
+        } else {
+            Self::Info
+        }
+    }
+}
+
+/// One line of R output. Payload of the `shiny-log` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct LogEntry {
+    /// Increases by one per line, so a reader can ask for what it missed.
+    pub seq: u64,
+    /// Milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    pub stream: LogStream,
+    pub level: LogLevel,
+    pub line: String,
+}
+
+#[derive(Default)]
+struct Buffer {
+    entries: VecDeque<LogEntry>,
+    next_seq: u64,
+}
+
+/// Bounded buffer of everything R printed, across restarts. Clones share
+/// the same buffer.
+#[derive(Clone)]
+pub struct ShinyLog {
+    buffer: Arc<Mutex<Buffer>>,
+    sink: StatusSink,
+}
+
+impl ShinyLog {
+    pub fn new(sink: StatusSink) -> Self {
+        Self {
+            buffer: Arc::new(Mutex::new(Buffer::default())),
+            sink,
+        }
+    }
+
+    /// Records a line, writes it to the log file and emits it as
+    /// `shiny-log`.
+    pub fn push(&self, stream: LogStream, line: &str) {
+        let level = LogLevel::classify(stream, line);
+        let log_level = match level {
+            LogLevel::Info => log::Level::Info,
+            LogLevel::Warning => log::Level::Warn,
+            LogLevel::Error => log::Level::Error,
+        };
+        log::log!(target: stream.target(), log_level, "{}", line);
+        let entry = {
+            let mut buffer = self.buffer.lock().unwrap();
+            let entry = LogEntry {
+                seq: buffer.next_seq,
+                timestamp: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_millis() as u64)
+                    .unwrap_or_default(),
+                stream,
+                level,
+                line: line.to_string(),
+            };
+            buffer.next_seq += 1;
+            if buffer.entries.len() == CAPACITY {
+                buffer.entries.pop_front();
+            }
+            buffer.entries.push_back(entry.clone());
+            entry
+        };
+        (self.sink)("shiny-log", serde_json::to_value(entry).unwrap_or_default());
+    }
+
+    /// The latest `limit` lines after `since`, at `level` or above.
+    pub fn query(
+        &self,
+        since: Option<u64>,
+        level: Option<LogLevel>,
+        limit: usize,
+    ) -> Vec<LogEntry> {
+        let buffer = self.buffer.lock().unwrap();
+        let mut entries: Vec<LogEntry> = buffer
+            .entries
+            .iter()
+            .rev()
+            .take_while(|e| since.map_or(true, |since| e.seq > since))
+            .filter(|e| level.map_or(true, |level| e.level >= level))
+            .take(limit)
+            .cloned()
+            .collect();
+        entries.reverse();
+        entries
+    }
+}
+
+/// Returns buffered R output, oldest first: lines after sequence number
+/// `since`, at `level` or above, at most `limit` of the latest. Reads the
+/// main app's output, or with `app_id` that of an app from the registry.
+#[tauri::command]
+pub fn get_shiny_logs(
+    supervisor: tauri::State<'_, ShinySupervisor>,
+    registry: tauri::State<'_, AppRegistry>,
+    app_id: Option<String>,
+    since: Option<u64>,
+    level: Option<LogLevel>,
+    limit: Option<usize>,
+) -> Result<Vec<LogEntry>, String> {
+    let supervisor = match &app_id {
+        Some(id) => registry.supervisor(id)?,
+        None => supervisor.inner(),
+    };
+    Ok(supervisor
+        .log()
+        .query(since, level, limit.unwrap_or(DEFAULT_LIMIT)))
+}
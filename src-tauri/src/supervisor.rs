@@ -0,0 +1,514 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::process::Child;
+use tokio::task::AbortHandle;
+
+use crate::process::{self, PidRecord};
+use crate::proxy::{self, Proxy, Target, Upstream};
+use crate::r_shiny::StatusSink;
+use crate::shiny_log::{LogStream, ShinyLog};
+
+/// Lifecycle of the R Shiny process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShinyState {
+    Idle,
+    Spawning,
+    LoadingPackages,
+    Ready,
+    Crashed,
+    Stopping,
+    Stopped,
+}
+
+/// Payload of the `shiny-state` event, emitted on every transition.
+#[derive(Debug, Clone, Serialize)]
+pub struct ShinyStateChange {
+    pub from: ShinyState,
+    pub to: ShinyState,
+    pub url: Option<String>,
+    pub pid: Option<u32>,
+    pub message: Option<String>,
+}
+
+/// Number of stderr lines kept for crash reports.
+const STDERR_TAIL_LINES: usize = 50;
+
+/// What is known about the last unexpected exit of R. Payload of the
+/// `shiny-crashed` event and kept for `get_shiny_state`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CrashReport {
+    pub exit_status: String,
+    pub exit_code: Option<i32>,
+    pub stderr_tail: Vec<String>,
+    /// Crashes within the configured window, including this one.
+    pub recent_crashes: usize,
+    /// Whether a restart will be attempted.
+    pub restarting: bool,
+    pub timestamp: u64,
+}
+
+/// Snapshot returned by `get_shiny_state`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ShinyStatus {
+    pub state: ShinyState,
+    pub url: Option<String>,
+    /// Port of the proxy the app is reached through.
+    pub port: Option<u16>,
+    pub pid: Option<u32>,
+    pub message: Option<String>,
+    pub last_crash: Option<CrashReport>,
+}
+
+struct Inner {
+    state: ShinyState,
+    child: Option<Child>,
+    /// Exit status of a process reaped while reading the state, kept until
+    /// `poll_exit` hands it to whoever acts on the exit.
+    exit: Option<ExitStatus>,
+    pid: Option<u32>,
+    /// The proxy's URL, with the launch's secret.
+    url: Option<String>,
+    /// R's private port, known only to the proxies.
+    port: Option<u16>,
+    /// Stands in front of R for the whole session, across crash restarts.
+    proxy: Option<Proxy>,
+    /// Sent by the proxies with every request; R refuses requests without
+    /// it. Lives as long as the proxy.
+    upstream_secret: Option<String>,
+    message: Option<String>,
+    /// Bumped whenever a launch starts or a stop is requested, so a pending
+    /// restart can tell it is no longer wanted.
+    generation: u64,
+    crashes: Vec<Instant>,
+    last_crash: Option<CrashReport>,
+    /// Set while a start is in progress; holds the task once spawned so
+    /// it can be cancelled.
+    starting: Option<StartClaim>,
+    /// Numbers each start, so a finished start can't release a newer one.
+    start_count: u64,
+}
+
+struct StartClaim {
+    id: u64,
+    task: Option<AbortHandle>,
+}
+
+/// Proof of a start claimed with `try_begin_start`, handed back to
+/// `end_start` to release it.
+#[must_use]
+pub struct StartToken(u64);
+
+/// Owns the R child process and tracks its state. Held in managed state by
+/// the app; the self-test creates its own. Clones share the same process.
+#[derive(Clone)]
+pub struct ShinySupervisor {
+    inner: Arc<Mutex<Inner>>,
+    stderr_tail: Arc<Mutex<VecDeque<String>>>,
+    log: ShinyLog,
+    sink: StatusSink,
+    pid_file: Option<PathBuf>,
+}
+
+impl ShinySupervisor {
+    pub fn new(sink: StatusSink) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                state: ShinyState::Idle,
+                child: None,
+                exit: None,
+                pid: None,
+                url: None,
+                port: None,
+                proxy: None,
+                upstream_secret: None,
+                message: None,
+                generation: 0,
+                crashes: Vec::new(),
+                last_crash: None,
+                starting: None,
+                start_count: 0,
+            })),
+            stderr_tail: Arc::new(Mutex::new(VecDeque::new())),
+            log: ShinyLog::new(sink.clone()),
+            sink,
+            pid_file: None,
+        }
+    }
+
+    /// Records the running process in `path` so a later session can clean
+    /// it up if this one dies without stopping it.
+    pub fn with_pid_file(mut self, path: PathBuf) -> Self {
+        self.pid_file = Some(path);
+        self
+    }
+
+    fn forget_pid(&self) {
+        if let Some(path) = &self.pid_file {
+            process::remove_pid_file(path);
+        }
+    }
+
+    pub fn sink(&self) -> &StatusSink {
+        &self.sink
+    }
+
+    fn transition(&self, inner: &mut Inner, to: ShinyState, message: Option<String>) {
+        let from = inner.state;
+        inner.state = to;
+        inner.message = message.clone();
+        log::info!("Shiny state: {:?} -> {:?}", from, to);
+        let change = ShinyStateChange {
+            from,
+            to,
+            url: inner.url.clone(),
+            pid: inner.pid,
+            message,
+        };
+        (self.sink)(
+            "shiny-state",
+            serde_json::to_value(change).unwrap_or_default(),
+        );
+    }
+
+    /// Starts a new launch, cancelling any pending restart. Returns the
+    /// generation the launch belongs to.
+    pub fn begin_session(&self) -> u64 {
+        let mut inner = self.inner.lock().unwrap();
+        inner.generation += 1;
+        inner.crashes.clear();
+        inner.generation
+    }
+
+    /// Claims the right to start Shiny. Returns `None` if a start is
+    /// already in progress.
+    pub fn try_begin_start(&self) -> Option<StartToken> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.starting.is_some() {
+            return None;
+        }
+        inner.start_count += 1;
+        let id = inner.start_count;
+        inner.starting = Some(StartClaim { id, task: None });
+        Some(StartToken(id))
+    }
+
+    /// Records the task running the start claimed with `token`, unless that
+    /// start was cancelled in the meantime.
+    pub fn set_start_task(&self, token: &StartToken, task: AbortHandle) {
+        match self.inner.lock().unwrap().starting.as_mut() {
+            Some(claim) if claim.id == token.0 => claim.task = Some(task),
+            _ => task.abort(),
+        }
+    }
+
+    /// Releases the claim, unless it was cancelled and a newer start has
+    /// claimed it since.
+    pub fn end_start(&self, token: StartToken) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner
+            .starting
+            .as_ref()
+            .is_some_and(|claim| claim.id == token.0)
+        {
+            inner.starting = None;
+        }
+    }
+
+    pub fn is_starting(&self) -> bool {
+        self.inner.lock().unwrap().starting.is_some()
+    }
+
+    /// Aborts the start in progress, if any. The caller still has to stop
+    /// a process the start may have spawned.
+    pub fn cancel_start(&self) -> bool {
+        match self.inner.lock().unwrap().starting.take() {
+            Some(claim) => {
+                if let Some(task) = claim.task {
+                    task.abort();
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_current(&self, generation: u64) -> bool {
+        self.inner.lock().unwrap().generation == generation
+    }
+
+    /// The current session, for tying something else to its lifetime.
+    pub fn session(&self) -> u64 {
+        self.inner.lock().unwrap().generation
+    }
+
+    /// Whether session `generation` is over: superseded by a stop or a new
+    /// launch, or crashed with no restart coming.
+    pub fn session_ended(&self, generation: u64) -> bool {
+        let inner = self.inner.lock().unwrap();
+        let gave_up = inner.state == ShinyState::Crashed
+            && inner.child.is_none()
+            && inner.last_crash.as_ref().is_some_and(|c| !c.restarting);
+        inner.generation != generation || gave_up
+    }
+
+    /// Where R serves the app right now and the secret it expects, for a
+    /// proxy in front of it. Doesn't keep the supervisor alive.
+    pub fn upstream(&self, host: IpAddr) -> Upstream {
+        let inner = Arc::downgrade(&self.inner);
+        Arc::new(move || {
+            let inner = inner.upgrade()?;
+            let inner = inner.lock().unwrap();
+            Some(Target {
+                addr: SocketAddr::new(host, inner.port?),
+                secret: inner.upstream_secret.clone()?,
+            })
+        })
+    }
+
+    /// Puts `proxy`, reached at `url`, in front of R for this session, with
+    /// a new secret for R.
+    pub fn set_proxy(&self, proxy: Proxy, url: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.proxy = Some(proxy);
+        inner.upstream_secret = Some(proxy::generate_token());
+        inner.url = Some(url.to_string());
+    }
+
+    /// The secret R must be started with, while a proxy is held.
+    pub fn upstream_secret(&self) -> Option<String> {
+        self.inner.lock().unwrap().upstream_secret.clone()
+    }
+
+    /// Whether a proxy is held, e.g. across a restart.
+    pub fn has_proxy(&self) -> bool {
+        self.inner.lock().unwrap().proxy.is_some()
+    }
+
+    /// Closes the proxy after a launch failed.
+    pub fn clear_proxy(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.proxy = None;
+        inner.upstream_secret = None;
+        inner.url = None;
+    }
+
+    pub fn url(&self) -> Option<String> {
+        self.inner.lock().unwrap().url.clone()
+    }
+
+    /// Whether a child process is currently held.
+    pub fn has_process(&self) -> bool {
+        self.inner.lock().unwrap().child.is_some()
+    }
+
+    /// What the current process wrote to stderr, latest last.
+    pub fn stderr_tail(&self) -> Vec<String> {
+        self.stderr_tail.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn log(&self) -> &ShinyLog {
+        &self.log
+    }
+
+    /// Logs a line R wrote; stderr is also kept for the next crash report.
+    pub fn record_line(&self, stream: LogStream, line: &str) {
+        self.log.push(stream, line);
+        if stream != LogStream::Stderr {
+            return;
+        }
+        let mut tail = self.stderr_tail.lock().unwrap();
+        if tail.len() == STDERR_TAIL_LINES {
+            tail.pop_front();
+        }
+        tail.push_back(line.to_string());
+    }
+
+    /// Records an unexpected exit and decides whether to restart: at most
+    /// `limit` crashes are tolerated within `window`. When giving up, moves
+    /// to `Crashed` for good and emits `shiny-crashed`.
+    pub fn record_crash(
+        &self,
+        exit_status: String,
+        exit_code: Option<i32>,
+        limit: u32,
+        window: Duration,
+    ) -> CrashReport {
+        let mut inner = self.inner.lock().unwrap();
+        let now = Instant::now();
+        inner.crashes.retain(|at| now.duration_since(*at) < window);
+        inner.crashes.push(now);
+
+        let report = CrashReport {
+            exit_status,
+            exit_code,
+            stderr_tail: self.stderr_tail(),
+            recent_crashes: inner.crashes.len(),
+            restarting: inner.crashes.len() <= limit as usize,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        };
+        inner.last_crash = Some(report.clone());
+
+        if !report.restarting {
+            let message = format!(
+                "R crashed {} times within {}s; not restarting",
+                report.recent_crashes,
+                window.as_secs()
+            );
+            log::error!("{}", message);
+            self.transition(&mut inner, ShinyState::Crashed, Some(message));
+            (self.sink)(
+                "shiny-crashed",
+                serde_json::to_value(&report).unwrap_or_default(),
+            );
+        }
+        report
+    }
+
+    pub fn set_state(&self, to: ShinyState, message: Option<String>) {
+        let mut inner = self.inner.lock().unwrap();
+        self.transition(&mut inner, to, message);
+    }
+
+    /// Records a freshly spawned process running `script`; R now loads the
+    /// app's packages.
+    pub fn attach(&self, child: Child, port: u16, script: &Path) {
+        if let (Some(path), Some(pid)) = (&self.pid_file, child.id()) {
+            let record = PidRecord {
+                pid,
+                script: script.to_path_buf(),
+            };
+            process::write_pid_file(path, &record);
+        }
+        let mut inner = self.inner.lock().unwrap();
+        inner.pid = child.id();
+        inner.child = Some(child);
+        inner.exit = None;
+        inner.port = Some(port);
+        self.stderr_tail.lock().unwrap().clear();
+        self.transition(&mut inner, ShinyState::LoadingPackages, None);
+    }
+
+    /// Records that R serves the app on the `port` it reported.
+    pub fn mark_ready(&self, port: u16) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.port = Some(port);
+        self.transition(&mut inner, ShinyState::Ready, None);
+    }
+
+    /// Reaps the process if it has exited on its own, moving to `Crashed`
+    /// and keeping the exit status for `poll_exit`.
+    fn reap(&self, inner: &mut Inner) {
+        let Some(Ok(Some(status))) = inner.child.as_mut().map(|child| child.try_wait()) else {
+            return;
+        };
+        inner.child = None;
+        inner.pid = None;
+        inner.exit = Some(status);
+        self.forget_pid();
+        if inner.state != ShinyState::Stopping {
+            let message = format!("R exited unexpectedly ({})", status);
+            self.transition(inner, ShinyState::Crashed, Some(message));
+        }
+    }
+
+    /// Checks whether the process has exited on its own and, if so, moves
+    /// to `Crashed` and returns its exit status. Each exit is returned once,
+    /// even if `status` noticed it first.
+    pub fn poll_exit(&self) -> Option<ExitStatus> {
+        let mut inner = self.inner.lock().unwrap();
+        self.reap(&mut inner);
+        inner.exit.take()
+    }
+
+    /// A snapshot of the state. Doesn't consume an exit, so the watcher
+    /// still restarts R after a crash noticed here.
+    pub fn status(&self) -> ShinyStatus {
+        let mut inner = self.inner.lock().unwrap();
+        self.reap(&mut inner);
+        ShinyStatus {
+            state: inner.state,
+            url: inner.url.clone(),
+            port: inner.proxy.as_ref().map(|p| p.local_addr().port()),
+            pid: inner.pid,
+            message: inner.message.clone(),
+            last_crash: inner.last_crash.clone(),
+        }
+    }
+
+    /// Shuts the R process down, if any, moving through `Stopping` to
+    /// `Stopped`, and cancels any pending restart. Closes the proxy, so the
+    /// launch's secret stops working. R gets `grace` to exit on its own
+    /// before its process group is killed.
+    pub async fn stop(&self, grace: Duration) -> Result<(), String> {
+        let child = {
+            let mut inner = self.inner.lock().unwrap();
+            inner.generation += 1;
+            inner.proxy = None;
+            inner.upstream_secret = None;
+            inner.url = None;
+            self.take_for_shutdown(&mut inner)
+        };
+        self.shutdown(child, grace).await
+    }
+
+    /// Like `stop`, but keeps the proxy and its secret for the launch that
+    /// follows, so open pages reconnect to it.
+    pub async fn stop_for_restart(&self, grace: Duration) -> Result<(), String> {
+        let child = {
+            let mut inner = self.inner.lock().unwrap();
+            inner.generation += 1;
+            self.take_for_shutdown(&mut inner)
+        };
+        self.shutdown(child, grace).await
+    }
+
+    /// Shuts the current process down without cancelling the session, e.g.
+    /// when it never became ready.
+    pub async fn discard(&self, grace: Duration) {
+        let child = self.take_for_shutdown(&mut self.inner.lock().unwrap());
+        let _ = self.shutdown(child, grace).await;
+    }
+
+    fn take_for_shutdown(&self, inner: &mut Inner) -> Option<Child> {
+        inner.exit = None;
+        let child = inner.child.take()?;
+        self.transition(inner, ShinyState::Stopping, None);
+        inner.pid = None;
+        Some(child)
+    }
+
+    /// Runs the shutdown sequence without holding the lock, so the state
+    /// stays readable while R is given time to exit.
+    async fn shutdown(&self, child: Option<Child>, grace: Duration) -> Result<(), String> {
+        let Some(mut child) = child else {
+            return Err("No R process running".to_string());
+        };
+        process::shutdown(&mut child, grace).await;
+        self.forget_pid();
+        log::info!("R process successfully terminated");
+
+        let mut inner = self.inner.lock().unwrap();
+        // A new launch may have started meanwhile. The URL stays: only
+        // `stop` ends the proxy's session.
+        if inner.state == ShinyState::Stopping && inner.child.is_none() {
+            inner.port = None;
+            self.transition(&mut inner, ShinyState::Stopped, None);
+        }
+        Ok(())
+    }
+}
+
+/// Returns the current state of the Shiny process.
+#[tauri::command]
+pub fn get_shiny_state(supervisor: tauri::State<'_, ShinySupervisor>) -> ShinyStatus {
+    supervisor.status()
+}
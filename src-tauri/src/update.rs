@@ -1,6 +1,9 @@
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use tauri::{AppHandle, Manager}; // Add Manager trait here
-use tauri_plugin_updater::UpdaterExt;
+use tauri_plugin_updater::{Update, UpdaterExt};
+
+use crate::runtime::DataDirs;
 
 pub struct UpdateState {
     pub progress: Arc<Mutex<UpdateProgress>>,
@@ -13,6 +16,7 @@ pub struct UpdateProgress {
     pub total: Option<u64>,
     pub complete: bool,
     pub error: Option<String>,
+    pub staged_path: Option<PathBuf>,
 }
 
 impl Default for UpdateProgress {
@@ -24,6 +28,7 @@ impl Default for UpdateProgress {
             total: None,
             complete: false,
             error: None,
+            staged_path: None,
         }
     }
 }
@@ -36,6 +41,7 @@ pub async fn check_for_updates(app_handle: AppHandle) -> Result<String, String>
     match updater.check().await {
         Ok(update) => {
             if let Some(update) = update {
+                log::info!("Update available: {}", update.version);
                 let update_info = serde_json::json!({
                     "available": true,
                     "version": update.version,
@@ -44,14 +50,36 @@ pub async fn check_for_updates(app_handle: AppHandle) -> Result<String, String>
                 });
                 Ok(serde_json::to_string(&update_info).unwrap())
             } else {
+                log::info!("No update available");
                 Ok(r#"{"available": false}"#.to_string())
             }
         }
-        Err(e) => Err(format!("Failed to check for updates: {}", e)),
+        Err(e) => {
+            log::warn!("Failed to check for updates: {}", e);
+            Err(format!("Failed to check for updates: {}", e))
+        }
     }
 }
 
-/// Downloads and installs the update.
+/// Writes a downloaded update package into `dir`, named after its URL.
+fn stage_update(dir: &Path, update: &Update, bytes: &[u8]) -> Result<PathBuf, String> {
+    let file_name = update
+        .download_url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| format!("grade-tool-tauri-{}", update.version));
+    let path = dir.join(file_name);
+    std::fs::write(&path, bytes)
+        .map_err(|e| format!("Failed to stage update at {}: {}", path.display(), e))?;
+    log::info!("Update staged at {}", path.display());
+    Ok(path)
+}
+
+/// Downloads and installs the update. In portable mode the package is only
+/// staged beside the executable, since running an installer would put the
+/// app into the user profile.
 #[tauri::command]
 pub async fn download_and_install_update(app_handle: AppHandle) -> Result<(), String> {
     let state = app_handle.state::<UpdateState>();
@@ -64,11 +92,15 @@ pub async fn download_and_install_update(app_handle: AppHandle) -> Result<(), St
         progress.downloaded = 0;
         progress.complete = false;
         progress.error = None;
+        progress.staged_path = None;
     }
 
     let updater = app_handle.updater().map_err(|e| e.to_string())?;
     let update = match updater.check().await {
-        Ok(Some(update)) => update,
+        Ok(Some(update)) => {
+            log::info!("Downloading update {}", update.version);
+            update
+        }
         Ok(None) => {
             let mut progress = progress_clone.lock().unwrap();
             progress.error = Some("No update available".to_string());
@@ -84,40 +116,53 @@ pub async fn download_and_install_update(app_handle: AppHandle) -> Result<(), St
     let progress_for_progress = progress_clone.clone();
     let progress_for_complete = progress_clone.clone();
 
-    match update
-        .download_and_install(
-            move |chunk_length, content_length| {
-                let mut progress = progress_for_progress.lock().unwrap();
-                progress.downloaded += chunk_length as u64;
-                progress.total = content_length;
-
-                if let Some(total) = content_length {
-                    if total > 0 {
-                        progress.percent = (progress.downloaded as f64 / total as f64) * 100.0;
-                    }
-                }
-
-                println!(
-                    "Downloaded {} of {} bytes ({:.1}%)",
-                    progress.downloaded,
-                    content_length.unwrap_or(0),
-                    progress.percent
-                );
-            },
-            move || {
-                let mut progress = progress_for_complete.lock().unwrap();
-                progress.complete = true;
-                progress.percent = 100.0;
-                println!("Download finished");
-            },
-        )
-        .await
-    {
+    let on_chunk = move |chunk_length: usize, content_length: Option<u64>| {
+        let mut progress = progress_for_progress.lock().unwrap();
+        progress.downloaded += chunk_length as u64;
+        progress.total = content_length;
+
+        if let Some(total) = content_length {
+            if total > 0 {
+                progress.percent = (progress.downloaded as f64 / total as f64) * 100.0;
+            }
+        }
+
+        log::debug!(
+            "Downloaded {} of {} bytes ({:.1}%)",
+            progress.downloaded,
+            content_length.unwrap_or(0),
+            progress.percent
+        );
+    };
+    let on_finish = move || {
+        let mut progress = progress_for_complete.lock().unwrap();
+        progress.complete = true;
+        progress.percent = 100.0;
+        log::info!("Download finished");
+    };
+
+    let data_dirs = app_handle.state::<DataDirs>();
+    let result = if data_dirs.portable {
+        match update.download(on_chunk, on_finish).await {
+            Ok(bytes) => stage_update(&data_dirs.updates, &update, &bytes).map(|path| {
+                progress_clone.lock().unwrap().staged_path = Some(path);
+            }),
+            Err(e) => Err(e.to_string()),
+        }
+    } else {
+        update
+            .download_and_install(on_chunk, on_finish)
+            .await
+            .map_err(|e| e.to_string())
+    };
+
+    match result {
         Ok(_) => Ok(()),
         Err(e) => {
+            log::error!("Failed to install update: {}", e);
             let mut progress = progress_clone.lock().unwrap();
-            progress.error = Some(e.to_string());
-            Err(e.to_string())
+            progress.error = Some(e.clone());
+            Err(e)
         }
     }
 }
@@ -134,7 +179,8 @@ pub fn get_update_progress(app_handle: AppHandle) -> Result<String, String> {
         "downloaded": progress.downloaded,
         "total": progress.total,
         "complete": progress.complete,
-        "error": progress.error
+        "error": progress.error,
+        "stagedPath": progress.staged_path
     });
 
     Ok(serde_json::to_string(&progress_json).unwrap())